@@ -19,6 +19,14 @@ if [ -z "$NO_RUN" ]; then
     cargo run --manifest-path systest/Cargo.toml --target $TARGET -vv
 fi
 
+if [ -n "$GETTEXTRS_FEATURES" ]
+then
+    cargo test --package gettext-rs --target $TARGET --features "$GETTEXTRS_FEATURES" --no-run -vv
+    if [ -z "$NO_RUN" ]; then
+        cargo test --package gettext-rs --target $TARGET --features "$GETTEXTRS_FEATURES" --verbose -- --nocapture
+    fi
+fi
+
 if [ -n "$FEATURES" ]
 then
     # We don't deny warnings here because we don't care about warnings in auto-generated code.
@@ -0,0 +1,15 @@
+use std::env;
+
+fn main() {
+    // gettext-sys only declares some libintl symbols if the linked implementation has them; use the
+    // same cfgs here
+    for (cfg, metadata) in &[
+        ("gettext_libintl_version", "DEP_GETTEXT_LIBINTL_VERSION"),
+        ("gettext_msg_cat_cntr", "DEP_GETTEXT_MSG_CAT_CNTR"),
+    ] {
+        println!("cargo:rustc-check-cfg=cfg({})", cfg);
+        if env::var_os(metadata).is_some() {
+            println!("cargo:rustc-cfg={}", cfg);
+        }
+    }
+}
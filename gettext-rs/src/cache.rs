@@ -0,0 +1,432 @@
+//! Cached lookups, for translations needed over and over, e.g. by the drawing code of a UI.
+//!
+//! The functions in this module work like the lookup functions of the same name in the crate root,
+//! but remember their results and return them as shared strings:
+//!
+//! ```no_run
+//! use gettextrs::*;
+//!
+//! TextDomain::new("my_textdomain").init().unwrap();
+//!
+//! for _frame in 0..1000 {
+//!     // Only the first iteration asks libintl
+//!     let title = cache::pgettext("Window title", "Settings");
+//!     # drop(title);
+//! }
+//! ```
+//!
+//! Translations are cached per domain, category, context, message, plural form, and the language
+//! of the current [`with_language`] scope. The cache is cleared whenever [`setlocale`],
+//! [`textdomain`], [`bindtextdomain`], [`bind_textdomain_codeset`], [`reload_catalogs`], or the
+//! [`TextDomain`] builder change what lookups return. Changes that are made without this crate,
+//! e.g. by calling libintl from C or by changing the `LANGUAGE` environment variable, aren't
+//! noticed; call [`clear`] after those.
+//!
+//! Lookups that come from the cache don't call the [missing translation hook], which only sees the
+//! first lookup of each message. [Mock translations](../testing/index.html) bypass the cache.
+//!
+//! [`with_language`]: ../fn.with_language.html
+//! [`setlocale`]: ../fn.setlocale.html
+//! [`textdomain`]: ../fn.textdomain.html
+//! [`bindtextdomain`]: ../fn.bindtextdomain.html
+//! [`bind_textdomain_codeset`]: ../fn.bind_textdomain_codeset.html
+//! [`reload_catalogs`]: ../fn.reload_catalogs.html
+//! [`TextDomain`]: ../struct.TextDomain.html
+//! [`clear`]: fn.clear.html
+//! [missing translation hook]: ../fn.set_missing_translation_hook.html
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+
+use super::catalog::PluralForms;
+use super::getters::CatalogMetadata;
+use super::{scope, testing, LocaleCategory};
+
+/// Incremented whenever cached translations go out of date.
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+static CACHE: RwLock<Option<Cache>> = RwLock::new(None);
+
+#[derive(Default)]
+struct Cache {
+    /// Value of `GENERATION` when the cache was filled.
+    generation: usize,
+    translations: HashMap<Key, Arc<str>>,
+    plural_forms: HashMap<Domain, Arc<PluralForms>>,
+}
+
+/// A lookup, without the number that plural lookups pick a form for.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Key {
+    domainname: Option<String>,
+    category: LocaleCategory,
+    msgctxt: Option<String>,
+    msgid: String,
+    msgid_plural: Option<String>,
+    /// Plural form for the number, and whether the number is 1, which is what libintl checks for
+    /// messages that aren't translated.
+    plural_form: Option<(usize, bool)>,
+    language: Option<String>,
+}
+
+/// A domain, the category it's looked up for, and the language of the `with_language` scope.
+type Domain = (Option<String>, LocaleCategory, Option<String>);
+
+impl Key {
+    fn new(
+        domainname: Option<String>,
+        category: LocaleCategory,
+        msgctxt: Option<String>,
+        msgid: String,
+        msgid_plural: Option<String>,
+    ) -> Key {
+        Key {
+            domainname,
+            category,
+            msgctxt,
+            msgid,
+            msgid_plural,
+            plural_form: None,
+            language: None,
+        }
+    }
+
+    /// Look up the message with the lookup function of the crate root that this key came from.
+    fn translate(&self, n: u32) -> String {
+        let msgid = self.msgid.as_str();
+        match (&self.domainname, &self.msgctxt, &self.msgid_plural) {
+            (None, None, None) => ::gettext(msgid),
+            (None, None, Some(plural)) => ::ngettext(msgid, plural.as_str(), n),
+            (None, Some(ctxt), None) => ::pgettext(ctxt.as_str(), msgid),
+            (None, Some(ctxt), Some(plural)) => {
+                ::npgettext(ctxt.as_str(), msgid, plural.as_str(), n)
+            }
+            (Some(domain), None, None) => ::dcgettext(domain.as_str(), msgid, self.category),
+            (Some(domain), None, Some(plural)) => {
+                ::dcngettext(domain.as_str(), msgid, plural.as_str(), n, self.category)
+            }
+            (Some(_), Some(_), _) => unreachable!("there are no lookups by domain and context"),
+        }
+    }
+}
+
+/// Mark all cached translations as out of date.
+pub(crate) fn invalidate() {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Drop all cached translations.
+///
+/// This crate does that by itself whenever it changes the locale or the text domains; call this if
+/// they were changed some other way, see the [module documentation](index.html).
+pub fn clear() {
+    invalidate();
+    *CACHE.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Get a value with `get`, or make it with `make` and store it with `insert`.
+fn cached<V: Clone>(
+    get: impl FnOnce(&Cache) -> Option<V>,
+    make: impl FnOnce() -> V,
+    insert: impl FnOnce(&mut Cache, V),
+) -> V {
+    let generation = GENERATION.load(Ordering::SeqCst);
+    if let Some(cache) = CACHE
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+    {
+        if cache.generation == generation {
+            if let Some(value) = get(cache) {
+                return value;
+            }
+        }
+    }
+
+    // Not under the lock: the missing translation hook might use the cache too
+    let value = make();
+
+    let mut cache = CACHE.write().unwrap_or_else(PoisonError::into_inner);
+    if GENERATION.load(Ordering::SeqCst) != generation {
+        // The configuration changed during the lookup, so the value might be out of date already
+        return value;
+    }
+    if !matches!(*cache, Some(ref cache) if cache.generation == generation) {
+        *cache = Some(Cache {
+            generation,
+            ..Cache::default()
+        });
+    }
+    if let Some(ref mut cache) = *cache {
+        insert(cache, value.clone());
+    }
+    value
+}
+
+fn translate(mut key: Key, n: u32) -> Arc<str> {
+    // Mock translations belong to the current thread, so they mustn't end up in the shared cache
+    if testing::active() {
+        return key.translate(n).into();
+    }
+
+    key.language = scope::current_language();
+    if key.msgid_plural.is_some() {
+        let index = plural_forms(&key).index(n.into());
+        key.plural_form = Some((index, n == 1));
+    }
+    cached(
+        |cache| cache.translations.get(&key).cloned(),
+        || key.translate(n).into(),
+        |cache, translation| {
+            cache.translations.insert(key.clone(), translation);
+        },
+    )
+}
+
+/// Plural forms of the catalog that `key` is looked up in.
+fn plural_forms(key: &Key) -> Arc<PluralForms> {
+    let domain = (key.domainname.clone(), key.category, key.language.clone());
+    cached(
+        |cache| cache.plural_forms.get(&domain).cloned(),
+        || {
+            let header = match key.domainname {
+                Some(ref domainname) => ::dcgettext(domainname.as_str(), "", key.category),
+                None => ::gettext(""),
+            };
+            let plural_forms = CatalogMetadata::from_header(&header)
+                .plural_forms
+                .and_then(|plural_forms| PluralForms::parse(&plural_forms))
+                .unwrap_or_default();
+            Arc::new(plural_forms)
+        },
+        |cache, plural_forms| {
+            cache.plural_forms.insert(domain.clone(), plural_forms);
+        },
+    )
+}
+
+/// Cached [`gettext`][fn@::gettext].
+///
+/// # Panics
+///
+/// Panics in the same cases as [`gettext`][fn@::gettext].
+pub fn gettext<T: Into<String>>(msgid: T) -> Arc<str> {
+    let key = Key::new(None, LocaleCategory::LcMessages, None, msgid.into(), None);
+    translate(key, 1)
+}
+
+/// Cached [`dgettext`][fn@::dgettext].
+///
+/// # Panics
+///
+/// Panics in the same cases as [`dgettext`][fn@::dgettext].
+pub fn dgettext<T, U>(domainname: T, msgid: U) -> Arc<str>
+where
+    T: Into<String>,
+    U: Into<String>,
+{
+    dcgettext(domainname, msgid, LocaleCategory::LcMessages)
+}
+
+/// Cached [`dcgettext`][fn@::dcgettext].
+///
+/// # Panics
+///
+/// Panics in the same cases as [`dcgettext`][fn@::dcgettext].
+pub fn dcgettext<T, U>(domainname: T, msgid: U, category: LocaleCategory) -> Arc<str>
+where
+    T: Into<String>,
+    U: Into<String>,
+{
+    let key = Key::new(Some(domainname.into()), category, None, msgid.into(), None);
+    translate(key, 1)
+}
+
+/// Cached [`ngettext`][fn@::ngettext].
+///
+/// # Panics
+///
+/// Panics in the same cases as [`ngettext`][fn@::ngettext].
+pub fn ngettext<T, S>(msgid: T, msgid_plural: S, n: u32) -> Arc<str>
+where
+    T: Into<String>,
+    S: Into<String>,
+{
+    let key = Key::new(
+        None,
+        LocaleCategory::LcMessages,
+        None,
+        msgid.into(),
+        Some(msgid_plural.into()),
+    );
+    translate(key, n)
+}
+
+/// Cached [`dngettext`][fn@::dngettext].
+///
+/// # Panics
+///
+/// Panics in the same cases as [`dngettext`][fn@::dngettext].
+pub fn dngettext<T, U, V>(domainname: T, msgid: U, msgid_plural: V, n: u32) -> Arc<str>
+where
+    T: Into<String>,
+    U: Into<String>,
+    V: Into<String>,
+{
+    dcngettext(
+        domainname,
+        msgid,
+        msgid_plural,
+        n,
+        LocaleCategory::LcMessages,
+    )
+}
+
+/// Cached [`dcngettext`][fn@::dcngettext].
+///
+/// # Panics
+///
+/// Panics in the same cases as [`dcngettext`][fn@::dcngettext].
+pub fn dcngettext<T, U, V>(
+    domainname: T,
+    msgid: U,
+    msgid_plural: V,
+    n: u32,
+    category: LocaleCategory,
+) -> Arc<str>
+where
+    T: Into<String>,
+    U: Into<String>,
+    V: Into<String>,
+{
+    let key = Key::new(
+        Some(domainname.into()),
+        category,
+        None,
+        msgid.into(),
+        Some(msgid_plural.into()),
+    );
+    translate(key, n)
+}
+
+/// Cached [`pgettext`][fn@::pgettext].
+///
+/// # Panics
+///
+/// Panics in the same cases as [`pgettext`][fn@::pgettext].
+pub fn pgettext<T, U>(msgctxt: T, msgid: U) -> Arc<str>
+where
+    T: Into<String>,
+    U: Into<String>,
+{
+    let key = Key::new(
+        None,
+        LocaleCategory::LcMessages,
+        Some(msgctxt.into()),
+        msgid.into(),
+        None,
+    );
+    translate(key, 1)
+}
+
+/// Cached [`npgettext`][fn@::npgettext].
+///
+/// # Panics
+///
+/// Panics in the same cases as [`npgettext`][fn@::npgettext].
+pub fn npgettext<T, U, V>(msgctxt: T, msgid: U, msgid_plural: V, n: u32) -> Arc<str>
+where
+    T: Into<String>,
+    U: Into<String>,
+    V: Into<String>,
+{
+    let key = Key::new(
+        None,
+        LocaleCategory::LcMessages,
+        Some(msgctxt.into()),
+        msgid.into(),
+        Some(msgid_plural.into()),
+    );
+    translate(key, n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing::MockTranslations;
+
+    #[test]
+    fn shared() {
+        // Keeps other tests from invalidating the cache
+        let _config = ::GettextConfig::lock();
+
+        let hello = dgettext("cache_shared", "Hello");
+        assert_eq!(&*hello, "Hello");
+        assert!(Arc::ptr_eq(&hello, &dgettext("cache_shared", "Hello")));
+        assert!(!Arc::ptr_eq(
+            &hello,
+            &dcgettext("cache_shared", "Hello", LocaleCategory::LcTime)
+        ));
+
+        let open = pgettext("File menu", "Open");
+        assert_eq!(&*open, "Open");
+        assert!(Arc::ptr_eq(&open, &pgettext("File menu", "Open")));
+        assert!(!Arc::ptr_eq(&open, &pgettext("Door", "Open")));
+
+        let one = dngettext("cache_shared", "One file", "{} files", 1);
+        let two = dngettext("cache_shared", "One file", "{} files", 2);
+        assert_eq!(&*one, "One file");
+        assert_eq!(&*two, "{} files");
+        assert!(Arc::ptr_eq(
+            &two,
+            &dngettext("cache_shared", "One file", "{} files", 5)
+        ));
+        assert_eq!(
+            &*npgettext("Inbox", "One message", "{} messages", 0),
+            "{} messages"
+        );
+    }
+
+    #[test]
+    fn invalidation() {
+        let config = ::GettextConfig::lock();
+
+        let hello = dgettext("cache_invalidation", "Hello");
+        config
+            .bind_textdomain_codeset("cache_invalidation", "UTF-8")
+            .unwrap();
+        let after_codeset = dgettext("cache_invalidation", "Hello");
+        assert!(!Arc::ptr_eq(&hello, &after_codeset));
+        assert!(Arc::ptr_eq(
+            &after_codeset,
+            &dgettext("cache_invalidation", "Hello")
+        ));
+
+        config.setlocale(LocaleCategory::LcMessages, "C");
+        let after_setlocale = dgettext("cache_invalidation", "Hello");
+        assert!(!Arc::ptr_eq(&after_codeset, &after_setlocale));
+
+        let current = ::getters::current_textdomain().unwrap();
+        config.textdomain(current).unwrap();
+        let after_textdomain = dgettext("cache_invalidation", "Hello");
+        assert!(!Arc::ptr_eq(&after_setlocale, &after_textdomain));
+
+        clear();
+        assert!(!Arc::ptr_eq(
+            &after_textdomain,
+            &dgettext("cache_invalidation", "Hello")
+        ));
+    }
+
+    #[test]
+    fn mocks() {
+        {
+            let _mock = MockTranslations::new("cache_mocks", "de")
+                .gettext("Hello", "Hallo")
+                .install();
+            assert_eq!(&*dgettext("cache_mocks", "Hello"), "Hallo");
+        }
+        assert_eq!(&*dgettext("cache_mocks", "Hello"), "Hello");
+    }
+}
@@ -0,0 +1,429 @@
+//! Reading MO files without libintl.
+//!
+//! This is used by [`with_language`](fn.with_language.html), which can't rely on libintl because
+//! libintl only knows about the process-wide locale.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use getters::CatalogMetadata;
+
+/// Magic number at the start of every MO file, in the byte order of the machine that wrote it.
+const MO_MAGIC: u32 = 0x9504_12de;
+
+/// The messages of one MO file.
+#[derive(Debug, Default)]
+pub(crate) struct Catalog {
+    /// Translations keyed by the msgid (including the context, if any). Messages with plural forms
+    /// have one translation per form.
+    messages: HashMap<String, Vec<String>>,
+    plural_forms: PluralForms,
+}
+
+impl Catalog {
+    /// Read the catalog from a file. `None` if the file can't be read or isn't a valid MO file.
+    pub(crate) fn load(path: &Path) -> Option<Catalog> {
+        Catalog::parse(&fs::read(path).ok()?)
+    }
+
+    /// Parse the contents of an MO file. `None` if they aren't valid.
+    ///
+    /// Only UTF-8 catalogs are supported; messages that aren't valid UTF-8 are skipped.
+    pub(crate) fn parse(data: &[u8]) -> Option<Catalog> {
+        let word = |offset: usize, big_endian: bool| -> Option<u32> {
+            let bytes = data.get(offset..offset.checked_add(4)?)?;
+            let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+            Some(if big_endian {
+                u32::from_be_bytes(bytes)
+            } else {
+                u32::from_le_bytes(bytes)
+            })
+        };
+        let big_endian = match word(0, false)? {
+            MO_MAGIC => false,
+            magic if magic.swap_bytes() == MO_MAGIC => true,
+            _ => return None,
+        };
+        // Only the major revision matters; minor revisions are backwards compatible
+        if word(4, big_endian)? >> 16 != 0 {
+            return None;
+        }
+        let count = word(8, big_endian)? as usize;
+        let originals = word(12, big_endian)? as usize;
+        let translations = word(16, big_endian)? as usize;
+
+        // Each table entry is a length and an offset
+        let string = |table: usize, index: usize| -> Option<&[u8]> {
+            let entry = table.checked_add(index.checked_mul(8)?)?;
+            let length = word(entry, big_endian)? as usize;
+            let offset = word(entry + 4, big_endian)? as usize;
+            data.get(offset..offset.checked_add(length)?)
+        };
+
+        // Every message takes 16 bytes in the tables, so a bogus count can't exhaust memory
+        let mut messages = HashMap::with_capacity(count.min(data.len() / 16));
+        for index in 0..count {
+            let original = string(originals, index)?;
+            let translation = string(translations, index)?;
+            // The msgid_plural follows the msgid, separated by a 0 byte
+            let msgid = original.split(|&b| b == 0).next().unwrap_or_default();
+            let msgid = match String::from_utf8(msgid.to_vec()) {
+                Ok(msgid) => msgid,
+                Err(_) => continue,
+            };
+            let forms: Result<Vec<String>, _> = translation
+                .split(|&b| b == 0)
+                .map(|form| String::from_utf8(form.to_vec()))
+                .collect();
+            if let Ok(forms) = forms {
+                messages.insert(msgid, forms);
+            }
+        }
+
+        let plural_forms = messages
+            .get("")
+            .and_then(|header| CatalogMetadata::from_header(&header[0]).plural_forms)
+            .and_then(|plural_forms| PluralForms::parse(&plural_forms))
+            .unwrap_or_default();
+
+        Some(Catalog {
+            messages,
+            plural_forms,
+        })
+    }
+
+    /// Add a message, replacing the translations it had (if any).
+    pub(crate) fn insert(&mut self, msgid: String, forms: Vec<String>) {
+        self.messages.insert(msgid, forms);
+    }
+
+    pub(crate) fn set_plural_forms(&mut self, plural_forms: PluralForms) {
+        self.plural_forms = plural_forms;
+    }
+
+    /// Translation of `msgid`, if the catalog has one.
+    pub(crate) fn gettext(&self, msgid: &str) -> Option<&str> {
+        self.messages.get(msgid).map(|forms| forms[0].as_str())
+    }
+
+    /// Translation of `msgid` for the number `n`, if the catalog has one.
+    pub(crate) fn ngettext(&self, msgid: &str, n: u32) -> Option<&str> {
+        let forms = self.messages.get(msgid)?;
+        let index = self.plural_forms.index(u64::from(n));
+        forms.get(index).map(String::as_str)
+    }
+}
+
+/// The `Plural-Forms` of a catalog, which pick a plural form for a number.
+#[derive(Debug, PartialEq)]
+pub(crate) struct PluralForms {
+    nplurals: usize,
+    plural: Expr,
+}
+
+impl Default for PluralForms {
+    /// Plural forms of Germanic languages, which gettext uses for catalogs that don't specify any.
+    fn default() -> PluralForms {
+        PluralForms {
+            nplurals: 2,
+            plural: Expr::Binary(Op::Ne, Box::new(Expr::N), Box::new(Expr::Number(1))),
+        }
+    }
+}
+
+impl PluralForms {
+    /// Parse a `Plural-Forms` value like `nplurals=2; plural=(n != 1);`.
+    pub(crate) fn parse(plural_forms: &str) -> Option<PluralForms> {
+        let mut nplurals = None;
+        let mut plural = None;
+        for field in plural_forms.split(';') {
+            let mut parts = field.splitn(2, '=');
+            match (parts.next().map(str::trim), parts.next()) {
+                (Some("nplurals"), Some(value)) => nplurals = value.trim().parse().ok(),
+                (Some("plural"), Some(value)) => plural = Expr::parse(value),
+                _ => {}
+            }
+        }
+        match (nplurals, plural) {
+            (Some(nplurals), Some(plural)) if nplurals > 0 => {
+                Some(PluralForms { nplurals, plural })
+            }
+            _ => None,
+        }
+    }
+
+    /// Index of the plural form for `n`.
+    pub(crate) fn index(&self, n: u64) -> usize {
+        let index = self.plural.eval(n);
+        if index < self.nplurals as u64 {
+            index as usize
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl Op {
+    /// Operators that bind tighter come later.
+    const PRECEDENCE: &'static [&'static [(&'static str, Op)]] = &[
+        &[("||", Op::Or)],
+        &[("&&", Op::And)],
+        &[("==", Op::Eq), ("!=", Op::Ne)],
+        &[("<=", Op::Le), (">=", Op::Ge), ("<", Op::Lt), (">", Op::Gt)],
+        &[("+", Op::Add), ("-", Op::Sub)],
+        &[("*", Op::Mul), ("/", Op::Div), ("%", Op::Rem)],
+    ];
+
+    fn apply(self, left: u64, right: u64) -> u64 {
+        match self {
+            Op::Or => u64::from(left != 0 || right != 0),
+            Op::And => u64::from(left != 0 && right != 0),
+            Op::Eq => u64::from(left == right),
+            Op::Ne => u64::from(left != right),
+            Op::Lt => u64::from(left < right),
+            Op::Gt => u64::from(left > right),
+            Op::Le => u64::from(left <= right),
+            Op::Ge => u64::from(left >= right),
+            Op::Add => left.wrapping_add(right),
+            Op::Sub => left.wrapping_sub(right),
+            Op::Mul => left.wrapping_mul(right),
+            Op::Div => left.checked_div(right).unwrap_or(0),
+            Op::Rem => left.checked_rem(right).unwrap_or(0),
+        }
+    }
+}
+
+/// The `plural` expression of `Plural-Forms`, which is written in a subset of C.
+#[derive(Debug, PartialEq)]
+enum Expr {
+    N,
+    Number(u64),
+    Not(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn parse(source: &str) -> Option<Expr> {
+        let tokens: String = source.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut rest = tokens.as_str();
+        let expr = Expr::parse_conditional(&mut rest)?;
+        if rest.is_empty() {
+            Some(expr)
+        } else {
+            None
+        }
+    }
+
+    fn parse_conditional(rest: &mut &str) -> Option<Expr> {
+        let condition = Expr::parse_binary(rest, 0)?;
+        if !eat(rest, "?") {
+            return Some(condition);
+        }
+        let then = Expr::parse_conditional(rest)?;
+        if !eat(rest, ":") {
+            return None;
+        }
+        let otherwise = Expr::parse_conditional(rest)?;
+        Some(Expr::Conditional(
+            Box::new(condition),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    fn parse_binary(rest: &mut &str, level: usize) -> Option<Expr> {
+        let operators = match Op::PRECEDENCE.get(level) {
+            Some(operators) => operators,
+            None => return Expr::parse_unary(rest),
+        };
+        let mut left = Expr::parse_binary(rest, level + 1)?;
+        while let Some(&(_, op)) = operators.iter().find(|(token, _)| eat(rest, token)) {
+            let right = Expr::parse_binary(rest, level + 1)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Some(left)
+    }
+
+    fn parse_unary(rest: &mut &str) -> Option<Expr> {
+        if eat(rest, "!") {
+            return Some(Expr::Not(Box::new(Expr::parse_unary(rest)?)));
+        }
+        if eat(rest, "(") {
+            let expr = Expr::parse_conditional(rest)?;
+            return if eat(rest, ")") { Some(expr) } else { None };
+        }
+        if eat(rest, "n") {
+            return Some(Expr::N);
+        }
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let number = rest[..digits].parse().ok()?;
+        *rest = &rest[digits..];
+        Some(Expr::Number(number))
+    }
+
+    fn eval(&self, n: u64) -> u64 {
+        match self {
+            Expr::N => n,
+            Expr::Number(number) => *number,
+            Expr::Not(expr) => u64::from(expr.eval(n) == 0),
+            Expr::Binary(op, left, right) => op.apply(left.eval(n), right.eval(n)),
+            Expr::Conditional(condition, then, otherwise) => {
+                if condition.eval(n) != 0 {
+                    then.eval(n)
+                } else {
+                    otherwise.eval(n)
+                }
+            }
+        }
+    }
+}
+
+/// Consume `token` if `rest` starts with it.
+///
+/// Operators that are prefixes of others (e.g. `<` of `<=`) must be tried last.
+fn eat(rest: &mut &str, token: &str) -> bool {
+    match rest.strip_prefix(token) {
+        Some(remainder) => {
+            *rest = remainder;
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Build an MO file with the given header and messages. A message with several translations
+    /// is given as `("msgid\0msgid_plural", &["form 0", "form 1", ...])`.
+    pub(crate) fn make_mo(header: &str, messages: &[(&str, &[&str])]) -> Vec<u8> {
+        let mut entries: Vec<(Vec<u8>, Vec<u8>)> = vec![(vec![], header.as_bytes().to_vec())];
+        for (msgid, forms) in messages {
+            entries.push((msgid.as_bytes().to_vec(), forms.join("\0").into_bytes()));
+        }
+        entries.sort();
+
+        let count = entries.len() as u32;
+        let originals = 28;
+        let translations = originals + 8 * count;
+        let mut strings_offset = translations + 8 * count;
+
+        let mut mo = Vec::new();
+        for word in &[MO_MAGIC, 0, count, originals, translations, 0, 0] {
+            mo.extend_from_slice(&word.to_le_bytes());
+        }
+        let mut strings = Vec::new();
+        let originals = entries.iter().map(|(msgid, _)| msgid);
+        let translations = entries.iter().map(|(_, translation)| translation);
+        for string in originals.chain(translations) {
+            mo.extend_from_slice(&(string.len() as u32).to_le_bytes());
+            mo.extend_from_slice(&strings_offset.to_le_bytes());
+            strings.extend_from_slice(string);
+            strings.push(0);
+            strings_offset += string.len() as u32 + 1;
+        }
+        mo.extend(strings);
+        mo
+    }
+
+    #[test]
+    fn parse_mo() {
+        let mo = make_mo(
+            "Content-Type: text/plain; charset=UTF-8\n\
+             Plural-Forms: nplurals=3; plural=(n%10==1 && n%100!=11 ? 0 : \
+             n%10>=2 && n%10<=4 && (n%100<10 || n%100>=20) ? 1 : 2);\n",
+            &[
+                ("Hello", &["Привет"]),
+                ("ctx\u{4}Open", &["Открыть"]),
+                ("One file\0{} files", &["{} файл", "{} файла", "{} файлов"]),
+            ],
+        );
+        let catalog = Catalog::parse(&mo).unwrap();
+
+        assert_eq!(catalog.gettext("Hello"), Some("Привет"));
+        assert_eq!(catalog.gettext("ctx\u{4}Open"), Some("Открыть"));
+        assert_eq!(catalog.gettext("Goodbye"), None);
+        assert_eq!(catalog.ngettext("One file", 1), Some("{} файл"));
+        assert_eq!(catalog.ngettext("One file", 3), Some("{} файла"));
+        assert_eq!(catalog.ngettext("One file", 11), Some("{} файлов"));
+        assert_eq!(catalog.ngettext("One file", 21), Some("{} файл"));
+        assert!(catalog.gettext("").unwrap().contains("Plural-Forms"));
+
+        let mut big_endian = mo.clone();
+        big_endian[..4].reverse();
+        assert!(Catalog::parse(&big_endian).is_none());
+        assert!(Catalog::parse(b"not an MO file").is_none());
+        assert!(Catalog::parse(&mo[..40]).is_none());
+    }
+
+    #[test]
+    fn plural_forms() {
+        let default = PluralForms::default();
+        assert_eq!(default.index(0), 1);
+        assert_eq!(default.index(1), 0);
+        assert_eq!(default.index(2), 1);
+
+        let single = PluralForms::parse("nplurals=1; plural=0;").unwrap();
+        assert_eq!(single.index(0), 0);
+        assert_eq!(single.index(5), 0);
+
+        let french = PluralForms::parse(" nplurals=2; plural=(n > 1);").unwrap();
+        assert_eq!(french.index(0), 0);
+        assert_eq!(french.index(1), 0);
+        assert_eq!(french.index(2), 1);
+
+        let polish = PluralForms::parse(
+            "nplurals=3; plural=(n==1 ? 0 : \
+             n%10>=2 && n%10<=4 && (n%100<10 || n%100>=20) ? 1 : 2);",
+        )
+        .unwrap();
+        let indices: Vec<usize> = [1, 2, 5, 12, 22, 25, 112]
+            .iter()
+            .map(|&n| polish.index(n))
+            .collect();
+        assert_eq!(indices, vec![0, 1, 2, 2, 1, 2, 2]);
+
+        // Indices out of range fall back to the first form
+        let broken = PluralForms::parse("nplurals=2; plural=n;").unwrap();
+        assert_eq!(broken.index(7), 0);
+
+        assert_eq!(PluralForms::parse("nplurals=2;"), None);
+        assert_eq!(PluralForms::parse("nplurals=2; plural=(n != 1;"), None);
+        assert_eq!(PluralForms::parse("nplurals=2; plural=n ! 1;"), None);
+        assert_eq!(PluralForms::parse("nplurals=0; plural=0;"), None);
+        assert_eq!(
+            PluralForms::parse("nplurals=2; plural=n/0;")
+                .unwrap()
+                .index(3),
+            0
+        );
+        assert_eq!(
+            PluralForms::parse("nplurals=3; plural=!n + !!n*2;")
+                .unwrap()
+                .index(0),
+            1
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Serializing changes to the process-wide gettext configuration.
+
+use std::io;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+
+use super::LocaleCategory;
+
+/// The thread that currently holds a [`GettextConfig`], and how many times it locked it.
+struct Owner {
+    thread: Option<ThreadId>,
+    depth: usize,
+}
+
+static OWNER: Mutex<Owner> = Mutex::new(Owner {
+    thread: None,
+    depth: 0,
+});
+static RELEASED: Condvar = Condvar::new();
+
+fn owner() -> MutexGuard<'static, Owner> {
+    OWNER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Exclusive access to the gettext configuration: the current locale, text domain, and the
+/// directories and codesets of text domains.
+///
+/// That configuration is global to the process, so threads that change it can step on each other's
+/// toes. Every function in this crate that reads or changes it ([`textdomain`][::textdomain],
+/// [`bindtextdomain`][::bindtextdomain], [`bind_textdomain_codeset`][::bind_textdomain_codeset],
+/// [`setlocale`][::setlocale], the [`getters`][::getters], [`TextDomain::init`], and
+/// [`reload_catalogs`]) holds a `GettextConfig` while doing so. Each of them is thus atomic, but a
+/// sequence of them isn't: another thread can change the configuration in between. To make several
+/// changes at once, hold a `GettextConfig` over all of them.
+///
+/// The lock is re-entrant: the thread that holds a `GettextConfig` can keep calling the functions
+/// listed above, and can even lock it again. Other threads block until all of its `GettextConfig`s
+/// are dropped. Translation lookups like [`gettext`][fn@::gettext] don't take the lock, except
+/// inside [`with_language`] (to find the catalogs).
+///
+/// Of course, this only serializes the changes made through this crate; changes made directly
+/// through the C API, e.g. by a C library, are not coordinated.
+///
+/// # Examples
+///
+/// ```no_run
+/// use gettextrs::*;
+///
+/// let config = GettextConfig::lock();
+/// config.setlocale(LocaleCategory::LcAll, "fr_FR.UTF-8");
+/// config.bind_textdomain_codeset("my_textdomain", "UTF-8")?;
+/// config.textdomain("my_textdomain")?;
+/// // Other threads only see the configuration once all three changes are made
+/// drop(config);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// [`TextDomain::init`]: struct.TextDomain.html#method.init
+/// [`reload_catalogs`]: fn.reload_catalogs.html
+/// [`with_language`]: fn.with_language.html
+#[derive(Debug)]
+pub struct GettextConfig {
+    // The lock belongs to the thread that took it
+    _not_send: PhantomData<*const ()>,
+}
+
+impl GettextConfig {
+    /// Lock the gettext configuration, blocking until no other thread holds it.
+    pub fn lock() -> GettextConfig {
+        let current = thread::current().id();
+        let mut owner = owner();
+        while owner.thread.is_some() && owner.thread != Some(current) {
+            owner = RELEASED.wait(owner).unwrap_or_else(|e| e.into_inner());
+        }
+        owner.thread = Some(current);
+        owner.depth += 1;
+        GettextConfig {
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Whether the current thread holds the lock.
+    pub(crate) fn is_held() -> bool {
+        owner().thread == Some(thread::current().id())
+    }
+
+    /// Switch to the specific text domain. See [`textdomain`][::textdomain].
+    pub fn textdomain<T: Into<Vec<u8>>>(&self, domainname: T) -> Result<Vec<u8>, io::Error> {
+        ::textdomain(domainname)
+    }
+
+    /// Specify the directory that contains MO files for the given domain. See
+    /// [`bindtextdomain`][::bindtextdomain].
+    pub fn bindtextdomain<T, U>(&self, domainname: T, dirname: U) -> Result<PathBuf, io::Error>
+    where
+        T: Into<Vec<u8>>,
+        U: Into<PathBuf>,
+    {
+        ::bindtextdomain(domainname, dirname)
+    }
+
+    /// Set encoding of translated messages. See
+    /// [`bind_textdomain_codeset`][::bind_textdomain_codeset].
+    pub fn bind_textdomain_codeset<T, U>(
+        &self,
+        domainname: T,
+        codeset: U,
+    ) -> Result<Option<String>, io::Error>
+    where
+        T: Into<Vec<u8>>,
+        U: Into<String>,
+    {
+        ::bind_textdomain_codeset(domainname, codeset)
+    }
+
+    /// Set current locale. See [`setlocale`][::setlocale].
+    pub fn setlocale<T: Into<Vec<u8>>>(
+        &self,
+        category: LocaleCategory,
+        locale: T,
+    ) -> Option<Vec<u8>> {
+        ::setlocale(category, locale)
+    }
+}
+
+impl Drop for GettextConfig {
+    fn drop(&mut self) {
+        let mut owner = owner();
+        owner.depth -= 1;
+        if owner.depth == 0 {
+            owner.thread = None;
+            RELEASED.notify_one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn reentrant() {
+        let config = GettextConfig::lock();
+        config.textdomain("config_reentrant").unwrap();
+        {
+            let _nested = GettextConfig::lock();
+            assert_eq!(
+                ::getters::current_textdomain().unwrap(),
+                b"config_reentrant"
+            );
+        }
+        assert_eq!(
+            ::textdomain("config_reentrant").unwrap(),
+            b"config_reentrant"
+        );
+    }
+
+    #[test]
+    fn exclusive() {
+        let config = GettextConfig::lock();
+        config.textdomain("config_exclusive").unwrap();
+
+        let (done, finished) = mpsc::channel();
+        let other = thread::spawn(move || {
+            ::textdomain("config_exclusive_other").unwrap();
+            done.send(()).unwrap();
+        });
+        assert!(finished.recv_timeout(Duration::from_millis(100)).is_err());
+        assert_eq!(
+            ::getters::current_textdomain().unwrap(),
+            b"config_exclusive"
+        );
+
+        drop(config);
+        finished.recv_timeout(Duration::from_secs(5)).unwrap();
+        other.join().unwrap();
+    }
+}
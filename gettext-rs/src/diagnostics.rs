@@ -0,0 +1,54 @@
+//! Diagnostics about setup and lookups, emitted through the `log` crate if the "log" feature is
+//! enabled.
+//!
+//! All records use the "gettextrs" target. Without the feature, the macros compile to nothing (but
+//! still type-check their arguments). There is no `tracing` support; `tracing` subscribers can
+//! pick up the records with `tracing-log`.
+
+#[cfg(feature = "log")]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        ::log::debug!(target: "gettextrs", $($arg)+)
+    };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    };
+}
+
+#[cfg(feature = "log")]
+macro_rules! trace {
+    ($($arg:tt)+) => {
+        ::log::trace!(target: "gettextrs", $($arg)+)
+    };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! trace {
+    ($($arg:tt)+) => {
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    };
+}
+
+#[cfg(feature = "log")]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        ::log::warn!(target: "gettextrs", $($arg)+)
+    };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    };
+}
@@ -26,6 +26,7 @@ use std::ptr;
 ///
 /// [textdomain(3)]: https://www.man7.org/linux/man-pages/man3/textdomain.3.html
 pub fn current_textdomain() -> Result<Vec<u8>, io::Error> {
+    let _config = ::GettextConfig::lock();
     unsafe {
         let result = ffi::textdomain(ptr::null());
         if result.is_null() {
@@ -51,6 +52,7 @@ pub fn current_textdomain() -> Result<Vec<u8>, io::Error> {
 /// underlying C API.
 pub fn domain_directory<T: Into<Vec<u8>>>(domainname: T) -> Result<PathBuf, io::Error> {
     let domainname = CString::new(domainname).expect("`domainname` contains an internal 0 byte");
+    let _config = ::GettextConfig::lock();
 
     #[cfg(windows)]
     {
@@ -111,6 +113,7 @@ pub fn domain_directory<T: Into<Vec<u8>>>(domainname: T) -> Result<PathBuf, io::
 ///     they're just codeset names).
 pub fn textdomain_codeset<T: Into<Vec<u8>>>(domainname: T) -> Result<Option<String>, io::Error> {
     let domainname = CString::new(domainname).expect("`domainname` contains an internal 0 byte");
+    let _config = ::GettextConfig::lock();
     unsafe {
         let result = ffi::bind_textdomain_codeset(domainname.as_ptr(), ptr::null());
         if result.is_null() {
@@ -129,3 +132,381 @@ pub fn textdomain_codeset<T: Into<Vec<u8>>>(domainname: T) -> Result<Option<Stri
         }
     }
 }
+
+/// Metadata stored in the header of a message catalog.
+///
+/// The header is the translation of the empty msgid `""`; it's a list of `Name: value` lines that
+/// describe the catalog. Fields that are absent from the header are `None`.
+///
+/// Use [`catalog_metadata`] to get the metadata of the catalog that is currently in use for a
+/// domain.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CatalogMetadata {
+    /// Name and version of the package the catalog belongs to (`Project-Id-Version`).
+    pub project_id_version: Option<String>,
+    /// Address to report bugs in the original strings to (`Report-Msgid-Bugs-To`).
+    pub report_msgid_bugs_to: Option<String>,
+    /// When the catalog was last updated by a translator (`PO-Revision-Date`).
+    pub po_revision_date: Option<String>,
+    /// Name and email of the last translator (`Last-Translator`).
+    pub last_translator: Option<String>,
+    /// Name and email of the translation team (`Language-Team`).
+    pub language_team: Option<String>,
+    /// Language of the catalog, e.g. "de" or "pt_BR" (`Language`).
+    pub language: Option<String>,
+    /// Plural formula of the language (`Plural-Forms`).
+    pub plural_forms: Option<String>,
+    /// Character set of the catalog, taken from the `Content-Type` field.
+    pub charset: Option<String>,
+    /// All fields of the header in the order they appear, including the ones above.
+    pub fields: Vec<(String, String)>,
+}
+
+impl CatalogMetadata {
+    /// Parse the header of a message catalog, i.e. the translation of the empty msgid.
+    ///
+    /// Lines that don't look like `Name: value` are ignored.
+    pub fn from_header(header: &str) -> CatalogMetadata {
+        let mut metadata = CatalogMetadata::default();
+
+        for line in header.lines() {
+            let mut parts = line.splitn(2, ':');
+            let name = parts.next().unwrap_or_default().trim();
+            let value = match parts.next() {
+                Some(value) if !name.is_empty() => value.trim(),
+                _ => continue,
+            };
+
+            match name {
+                "Project-Id-Version" => metadata.project_id_version = Some(value.to_owned()),
+                "Report-Msgid-Bugs-To" => metadata.report_msgid_bugs_to = Some(value.to_owned()),
+                "PO-Revision-Date" => metadata.po_revision_date = Some(value.to_owned()),
+                "Last-Translator" => metadata.last_translator = Some(value.to_owned()),
+                "Language-Team" => metadata.language_team = Some(value.to_owned()),
+                "Language" => metadata.language = Some(value.to_owned()),
+                "Plural-Forms" => metadata.plural_forms = Some(value.to_owned()),
+                "Content-Type" => {
+                    metadata.charset = value
+                        .split(';')
+                        .filter_map(|param| {
+                            let mut param = param.splitn(2, '=');
+                            match (param.next(), param.next()) {
+                                (Some(key), Some(charset)) if key.trim() == "charset" => {
+                                    Some(charset.trim().to_owned())
+                                }
+                                _ => None,
+                            }
+                        })
+                        .next()
+                }
+                _ => {}
+            }
+
+            metadata.fields.push((name.to_owned(), value.to_owned()));
+        }
+
+        metadata
+    }
+}
+
+/// Get the header metadata of the catalog used for the given domain in the current locale.
+///
+/// Returns `None` if gettext has no catalog for the domain in the current locale (or if the
+/// catalog has no header).
+///
+/// The header is looked up like any other message, so the domain has to be bound (see
+/// [`bindtextdomain`][::bindtextdomain]) and the locale has to be set (see
+/// [`setlocale`][::setlocale]) beforehand. It always comes from libintl: pseudo-localization,
+/// [mock translations](../testing/index.html) and [`with_language`][::with_language] scopes don't
+/// affect it.
+///
+/// # Panics
+///
+/// Panics if:
+/// * `domainname` contains an internal 0 byte, as such values can't be passed to the underlying
+///     C API;
+/// * the header is not in UTF-8 (see [this note](../index.html#utf-8-is-required)).
+pub fn catalog_metadata<T: Into<String>>(domainname: T) -> Option<CatalogMetadata> {
+    let domainname =
+        CString::new(domainname.into()).expect("`domainname` contains an internal 0 byte");
+    let empty = CString::default();
+    let _config = ::GettextConfig::lock();
+    let header = unsafe {
+        CStr::from_ptr(ffi::dgettext(domainname.as_ptr(), empty.as_ptr()))
+            .to_str()
+            .expect("dgettext() returned invalid UTF-8")
+    };
+    if header.is_empty() {
+        None
+    } else {
+        Some(CatalogMetadata::from_header(header))
+    }
+}
+
+/// Implementation of libintl that this crate uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibintlImplementation {
+    /// The GNU C Library, which has libintl built in.
+    Glibc,
+    /// GNU libintl, from the GNU gettext package.
+    GnuLibintl,
+    /// Any other implementation, e.g. the one in musl.
+    Other,
+}
+
+/// Get the implementation of libintl that this crate uses.
+///
+/// On Unix, this is detected at runtime from the library that `dgettext` was loaded from: a
+/// `libintl` library is GNU libintl, and glibc's `libc.so` is glibc. If libintl is linked
+/// statically, or on other platforms, the implementation that the crate was built against is
+/// reported instead: only GNU libintl's `libintl.h` defines `LIBINTL_VERSION`, and on glibc
+/// targets without it, libintl is the one built into the C library.
+pub fn libintl_implementation() -> LibintlImplementation {
+    loaded::dgettext_object()
+        .and_then(|object| implementation_of(&object))
+        .unwrap_or_else(built_against)
+}
+
+/// The implementation that a shared object with the given file name provides, if it's a
+/// recognized one.
+fn implementation_of(object: &str) -> Option<LibintlImplementation> {
+    if object.starts_with("libintl.") || object.starts_with("libintl-") {
+        Some(LibintlImplementation::GnuLibintl)
+    } else if object.starts_with("libc.so") {
+        if cfg!(all(target_os = "linux", target_env = "gnu")) {
+            Some(LibintlImplementation::Glibc)
+        } else {
+            Some(LibintlImplementation::Other)
+        }
+    } else if object.starts_with("ld-musl-") {
+        Some(LibintlImplementation::Other)
+    } else {
+        None
+    }
+}
+
+/// The implementation that the crate was built against, according to the headers that
+/// gettext-sys found.
+fn built_against() -> LibintlImplementation {
+    if cfg!(gettext_libintl_version) {
+        LibintlImplementation::GnuLibintl
+    } else if cfg!(all(
+        gettext_msg_cat_cntr,
+        target_os = "linux",
+        target_env = "gnu"
+    )) {
+        LibintlImplementation::Glibc
+    } else {
+        LibintlImplementation::Other
+    }
+}
+
+/// Get the version of libintl that this crate is linked with, e.g. "0.21" for GNU libintl or
+/// "2.35" for glibc.
+///
+/// The version is read at runtime from the library that got loaded (see
+/// [`libintl_implementation`]), so it may differ from the one whose headers were used. `None` if
+/// the implementation doesn't report its version, or if it's GNU libintl but the crate was built
+/// against other headers, which don't declare the version.
+///
+/// [`libintl_implementation`]: fn.libintl_implementation.html
+pub fn libintl_version() -> Option<String> {
+    match libintl_implementation() {
+        LibintlImplementation::GnuLibintl => gnu_libintl_version(),
+        LibintlImplementation::Glibc => glibc_version(),
+        LibintlImplementation::Other => None,
+    }
+}
+
+#[cfg(gettext_libintl_version)]
+fn gnu_libintl_version() -> Option<String> {
+    Some(format_libintl_version(unsafe { ffi::libintl_version }))
+}
+
+#[cfg(not(gettext_libintl_version))]
+fn gnu_libintl_version() -> Option<String> {
+    None
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn glibc_version() -> Option<String> {
+    unsafe {
+        CStr::from_ptr(ffi::gnu_get_libc_version())
+            .to_str()
+            .ok()
+            .map(String::from)
+    }
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+fn glibc_version() -> Option<String> {
+    None
+}
+
+#[cfg(unix)]
+mod loaded {
+    use std::ffi::CStr;
+    use std::os::raw::{c_char, c_int, c_void};
+    use std::path::Path;
+
+    use super::ffi;
+
+    /// `Dl_info` from `dlfcn.h`.
+    #[repr(C)]
+    struct DlInfo {
+        dli_fname: *const c_char,
+        dli_fbase: *mut c_void,
+        dli_sname: *const c_char,
+        dli_saddr: *mut c_void,
+    }
+
+    extern "C" {
+        fn dladdr(addr: *const c_void, info: *mut DlInfo) -> c_int;
+    }
+
+    /// File name of the shared object (or the executable) that `dgettext` was loaded from.
+    pub(super) fn dgettext_object() -> Option<String> {
+        let dgettext = ffi::dgettext as *const c_void;
+        let mut info = DlInfo {
+            dli_fname: std::ptr::null(),
+            dli_fbase: std::ptr::null_mut(),
+            dli_sname: std::ptr::null(),
+            dli_saddr: std::ptr::null_mut(),
+        };
+        if unsafe { dladdr(dgettext, &mut info) } == 0 || info.dli_fname.is_null() {
+            return None;
+        }
+        let path = unsafe { CStr::from_ptr(info.dli_fname) }.to_string_lossy();
+        Path::new(&*path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(not(unix))]
+mod loaded {
+    pub(super) fn dgettext_object() -> Option<String> {
+        None
+    }
+}
+
+/// Format a `LIBINTL_VERSION`-style number (0xMMmmss) as "M.m" or "M.m.s".
+#[cfg_attr(not(gettext_libintl_version), allow(dead_code))]
+fn format_libintl_version(version: i32) -> String {
+    let (major, minor, subminor) = (version >> 16, (version >> 8) & 0xff, version & 0xff);
+    if subminor == 0 {
+        format!("{}.{}", major, minor)
+    } else {
+        format!("{}.{}.{}", major, minor, subminor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        catalog_metadata, format_libintl_version, implementation_of, CatalogMetadata,
+        LibintlImplementation,
+    };
+    use testing::MockTranslations;
+
+    #[test]
+    fn parses_catalog_header() {
+        let header = "Project-Id-Version: hellorust 1.0\n\
+                      PO-Revision-Date: 2021-04-25 12:00+0300\n\
+                      Last-Translator: Jane Doe <jane@example.com>\n\
+                      Language-Team: German <de@example.com>\n\
+                      Language: de\n\
+                      MIME-Version: 1.0\n\
+                      Content-Type: text/plain; charset=UTF-8\n\
+                      Content-Transfer-Encoding: 8bit\n\
+                      Plural-Forms: nplurals=2; plural=(n != 1);\n";
+
+        let metadata = CatalogMetadata::from_header(header);
+        assert_eq!(
+            metadata.project_id_version.as_deref(),
+            Some("hellorust 1.0")
+        );
+        assert_eq!(
+            metadata.po_revision_date.as_deref(),
+            Some("2021-04-25 12:00+0300")
+        );
+        assert_eq!(
+            metadata.last_translator.as_deref(),
+            Some("Jane Doe <jane@example.com>")
+        );
+        assert_eq!(
+            metadata.language_team.as_deref(),
+            Some("German <de@example.com>")
+        );
+        assert_eq!(metadata.language.as_deref(), Some("de"));
+        assert_eq!(metadata.charset.as_deref(), Some("UTF-8"));
+        assert_eq!(
+            metadata.plural_forms.as_deref(),
+            Some("nplurals=2; plural=(n != 1);")
+        );
+        assert_eq!(metadata.report_msgid_bugs_to, None);
+        assert_eq!(metadata.fields.len(), 9);
+        assert_eq!(
+            metadata.fields[5],
+            ("MIME-Version".to_owned(), "1.0".to_owned())
+        );
+    }
+
+    #[test]
+    fn ignores_malformed_header_lines() {
+        let metadata = CatalogMetadata::from_header("garbage\n: no name\nLanguage: fr\n");
+        assert_eq!(metadata.language.as_deref(), Some("fr"));
+        assert_eq!(
+            metadata.fields,
+            vec![("Language".to_owned(), "fr".to_owned())]
+        );
+    }
+
+    #[test]
+    fn libintl_versions() {
+        assert_eq!(format_libintl_version(0x001500), "0.21");
+        assert_eq!(format_libintl_version(0x001501), "0.21.1");
+        assert_eq!(format_libintl_version(0x010203), "1.2.3");
+    }
+
+    #[test]
+    fn libintl_implementations() {
+        use super::LibintlImplementation::*;
+
+        assert_eq!(implementation_of("libintl.so.8"), Some(GnuLibintl));
+        assert_eq!(implementation_of("libintl.8.dylib"), Some(GnuLibintl));
+        assert_eq!(implementation_of("libintl-8.dll"), Some(GnuLibintl));
+        assert_eq!(implementation_of("ld-musl-x86_64.so.1"), Some(Other));
+        // A statically linked libintl is part of the executable
+        assert_eq!(implementation_of("my_program"), None);
+        assert_eq!(implementation_of("libintlfoo.so"), None);
+
+        if cfg!(all(target_os = "linux", target_env = "gnu")) {
+            assert_eq!(implementation_of("libc.so.6"), Some(Glibc));
+        } else {
+            assert_eq!(implementation_of("libc.so.6"), Some(Other));
+        }
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", target_env = "gnu", not(gettext_libintl_version)))]
+    fn detects_glibc() {
+        assert_eq!(
+            super::loaded::dgettext_object().as_deref(),
+            Some("libc.so.6")
+        );
+        assert_eq!(
+            super::libintl_implementation(),
+            LibintlImplementation::Glibc
+        );
+    }
+
+    #[test]
+    fn catalog_metadata_ignores_mocks() {
+        let _mock = MockTranslations::new("getters_mocked_header", "de")
+            .gettext("", "Language: de\n")
+            .install();
+        assert_eq!(::dgettext("getters_mocked_header", ""), "Language: de\n");
+        assert_eq!(catalog_metadata("getters_mocked_header"), None);
+    }
+}
@@ -78,49 +78,110 @@
 //! be done with that.
 
 extern crate locale_config;
+#[cfg(feature = "log")]
+extern crate log;
 
 extern crate gettext_sys as ffi;
 
 use std::ffi::CStr;
 use std::ffi::CString;
 use std::io;
-use std::os::raw::c_ulong;
+use std::os::raw::{c_int, c_ulong};
 use std::path::PathBuf;
 
+#[macro_use]
+mod diagnostics;
+
+mod catalog;
+mod config;
 mod macros;
+mod missing;
+mod number;
+mod pseudo;
+mod reload;
+mod scope;
 mod text_domain;
-pub use text_domain::{TextDomain, TextDomainError};
+pub use config::GettextConfig;
+#[doc(hidden)]
+pub use missing::with_location as __with_location;
+pub use missing::{
+    clear_missing_translation_hook, set_missing_translation_hook, MissingTranslation,
+    SourceLocation,
+};
+pub use number::LocalizedNumber;
+pub use pseudo::{disable_pseudolocalization, enable_pseudolocalization, pseudolocalize};
+pub use reload::{reload_catalogs, watch_catalogs, CatalogWatcher};
+pub use scope::{with_language, WithLanguage};
+pub use text_domain::{
+    DomainResolution, TextDomain, TextDomainError, TextDomainInit, TextDomainResolution,
+};
+pub mod cache;
 pub mod getters;
+pub mod locale_info;
+pub mod testing;
 
 /// Locale category enum ported from locale.h.
-#[derive(Debug, PartialEq, Clone, Copy)]
+///
+/// The values passed to the underlying C API are taken from the target's `locale.h` at build time
+/// (see the `LC_*` constants in gettext-sys). Not every target has every category: [`setlocale`]
+/// fails for missing ones, and the `dc*` functions return the message untranslated.
+///
+/// [`setlocale`]: fn.setlocale.html
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum LocaleCategory {
     /// Character classification and case conversion.
-    LcCType = 0,
+    LcCType,
     /// Non-monetary numeric formats.
-    LcNumeric = 1,
+    LcNumeric,
     /// Date and time formats.
-    LcTime = 2,
+    LcTime,
     /// Collation order.
-    LcCollate = 3,
+    LcCollate,
     /// Monetary formats.
-    LcMonetary = 4,
+    LcMonetary,
     /// Formats of informative and diagnostic messages and interactive responses.
-    LcMessages = 5,
+    LcMessages,
     /// For all.
-    LcAll = 6,
+    LcAll,
     /// Paper size.
-    LcPaper = 7,
+    LcPaper,
     /// Name formats.
-    LcName = 8,
+    LcName,
     /// Address formats and location information.
-    LcAddress = 9,
+    LcAddress,
     /// Telephone number formats.
-    LcTelephone = 10,
+    LcTelephone,
     /// Measurement units (Metric or Other).
-    LcMeasurement = 11,
+    LcMeasurement,
     /// Metadata about the locale information.
-    LcIdentification = 12,
+    LcIdentification,
+}
+
+impl LocaleCategory {
+    /// The value of this category in the target's `locale.h`, or `None` if the target doesn't
+    /// have it.
+    fn to_raw(self) -> Option<c_int> {
+        let raw = match self {
+            LocaleCategory::LcCType => ffi::LC_CTYPE,
+            LocaleCategory::LcNumeric => ffi::LC_NUMERIC,
+            LocaleCategory::LcTime => ffi::LC_TIME,
+            LocaleCategory::LcCollate => ffi::LC_COLLATE,
+            LocaleCategory::LcMonetary => ffi::LC_MONETARY,
+            LocaleCategory::LcMessages => ffi::LC_MESSAGES,
+            LocaleCategory::LcAll => ffi::LC_ALL,
+            LocaleCategory::LcPaper => ffi::LC_PAPER,
+            LocaleCategory::LcName => ffi::LC_NAME,
+            LocaleCategory::LcAddress => ffi::LC_ADDRESS,
+            LocaleCategory::LcTelephone => ffi::LC_TELEPHONE,
+            LocaleCategory::LcMeasurement => ffi::LC_MEASUREMENT,
+            LocaleCategory::LcIdentification => ffi::LC_IDENTIFICATION,
+        };
+        if raw == -1 {
+            None
+        } else {
+            Some(raw)
+        }
+    }
 }
 
 /// Translate msgid to localized message from the default domain.
@@ -137,8 +198,19 @@ pub enum LocaleCategory {
 /// * the result is not in UTF-8 (see [this note](./index.html#utf-8-is-required)).
 pub fn gettext<T: Into<String>>(msgid: T) -> String {
     let msgid = CString::new(msgid.into()).expect("`msgid` contains an internal 0 byte");
+    if let Some(translation) = pseudo::translate(&msgid, None) {
+        return translation;
+    }
+    if let Some(translation) = testing::translate(None, LocaleCategory::LcMessages, &msgid, None) {
+        return translation;
+    }
+    if let Some(translation) = scope::translate(None, LocaleCategory::LcMessages, &msgid, None) {
+        return translation;
+    }
     unsafe {
-        CStr::from_ptr(ffi::gettext(msgid.as_ptr()))
+        let translation = ffi::gettext(msgid.as_ptr());
+        missing::check(translation, None, LocaleCategory::LcMessages, &msgid, None);
+        CStr::from_ptr(translation)
             .to_str()
             .expect("gettext() returned invalid UTF-8")
             .to_owned()
@@ -166,8 +238,23 @@ where
     let domainname =
         CString::new(domainname.into()).expect("`domainname` contains an internal 0 byte");
     let msgid = CString::new(msgid.into()).expect("`msgid` contains an internal 0 byte");
+    if let Some(translation) = pseudo::translate(&msgid, None) {
+        return translation;
+    }
+    if let Some(translation) =
+        testing::translate(Some(&domainname), LocaleCategory::LcMessages, &msgid, None)
+    {
+        return translation;
+    }
+    let scoped = scope::translate(Some(&domainname), LocaleCategory::LcMessages, &msgid, None);
+    if let Some(translation) = scoped {
+        return translation;
+    }
     unsafe {
-        CStr::from_ptr(ffi::dgettext(domainname.as_ptr(), msgid.as_ptr()))
+        let translation = ffi::dgettext(domainname.as_ptr(), msgid.as_ptr());
+        let category = LocaleCategory::LcMessages;
+        missing::check(translation, Some(&domainname), category, &msgid, None);
+        CStr::from_ptr(translation)
             .to_str()
             .expect("dgettext() returned invalid UTF-8")
             .to_owned()
@@ -194,15 +281,30 @@ where
     let domainname =
         CString::new(domainname.into()).expect("`domainname` contains an internal 0 byte");
     let msgid = CString::new(msgid.into()).expect("`msgid` contains an internal 0 byte");
+    if let Some(translation) = pseudo::translate(&msgid, None) {
+        return translation;
+    }
+    if let Some(translation) = testing::translate(Some(&domainname), category, &msgid, None) {
+        return translation;
+    }
+    if let Some(translation) = scope::translate(Some(&domainname), category, &msgid, None) {
+        return translation;
+    }
+    let raw_category = match category.to_raw() {
+        Some(category) => category,
+        // There can't be any translations for a category the target doesn't have
+        None => {
+            missing::report(Some(&domainname), category, &msgid, None);
+            return msgid.into_string().unwrap();
+        }
+    };
     unsafe {
-        CStr::from_ptr(ffi::dcgettext(
-            domainname.as_ptr(),
-            msgid.as_ptr(),
-            category as i32,
-        ))
-        .to_str()
-        .expect("dcgettext() returned invalid UTF-8")
-        .to_owned()
+        let translation = ffi::dcgettext(domainname.as_ptr(), msgid.as_ptr(), raw_category);
+        missing::check(translation, Some(&domainname), category, &msgid, None);
+        CStr::from_ptr(translation)
+            .to_str()
+            .expect("dcgettext() returned invalid UTF-8")
+            .to_owned()
     }
 }
 
@@ -226,15 +328,30 @@ where
     let msgid = CString::new(msgid.into()).expect("`msgid` contains an internal 0 byte");
     let msgid_plural =
         CString::new(msgid_plural.into()).expect("`msgid_plural` contains an internal 0 byte");
+    let plural = Some((msgid_plural.as_c_str(), n));
+    if let Some(translation) = pseudo::translate(&msgid, plural) {
+        return translation;
+    }
+    if let Some(translation) = testing::translate(None, LocaleCategory::LcMessages, &msgid, plural)
+    {
+        return translation;
+    }
+    if let Some(translation) = scope::translate(None, LocaleCategory::LcMessages, &msgid, plural) {
+        return translation;
+    }
     unsafe {
-        CStr::from_ptr(ffi::ngettext(
-            msgid.as_ptr(),
-            msgid_plural.as_ptr(),
-            n as c_ulong,
-        ))
-        .to_str()
-        .expect("ngettext() returned invalid UTF-8")
-        .to_owned()
+        let translation = ffi::ngettext(msgid.as_ptr(), msgid_plural.as_ptr(), n as c_ulong);
+        missing::check(
+            translation,
+            None,
+            LocaleCategory::LcMessages,
+            &msgid,
+            plural,
+        );
+        CStr::from_ptr(translation)
+            .to_str()
+            .expect("ngettext() returned invalid UTF-8")
+            .to_owned()
     }
 }
 
@@ -261,16 +378,40 @@ where
     let msgid = CString::new(msgid.into()).expect("`msgid` contains an internal 0 byte");
     let msgid_plural =
         CString::new(msgid_plural.into()).expect("`msgid_plural` contains an internal 0 byte");
+    let plural = Some((msgid_plural.as_c_str(), n));
+    if let Some(translation) = pseudo::translate(&msgid, plural) {
+        return translation;
+    }
+    if let Some(translation) = testing::translate(
+        Some(&domainname),
+        LocaleCategory::LcMessages,
+        &msgid,
+        plural,
+    ) {
+        return translation;
+    }
+    let scoped = scope::translate(
+        Some(&domainname),
+        LocaleCategory::LcMessages,
+        &msgid,
+        plural,
+    );
+    if let Some(translation) = scoped {
+        return translation;
+    }
     unsafe {
-        CStr::from_ptr(ffi::dngettext(
+        let translation = ffi::dngettext(
             domainname.as_ptr(),
             msgid.as_ptr(),
             msgid_plural.as_ptr(),
             n as c_ulong,
-        ))
-        .to_str()
-        .expect("dngettext() returned invalid UTF-8")
-        .to_owned()
+        );
+        let category = LocaleCategory::LcMessages;
+        missing::check(translation, Some(&domainname), category, &msgid, plural);
+        CStr::from_ptr(translation)
+            .to_str()
+            .expect("dngettext() returned invalid UTF-8")
+            .to_owned()
     }
 }
 
@@ -304,17 +445,40 @@ where
     let msgid = CString::new(msgid.into()).expect("`msgid` contains an internal 0 byte");
     let msgid_plural =
         CString::new(msgid_plural.into()).expect("`msgid_plural` contains an internal 0 byte");
+    let plural = Some((msgid_plural.as_c_str(), n));
+    if let Some(translation) = pseudo::translate(&msgid, plural) {
+        return translation;
+    }
+    if let Some(translation) = testing::translate(Some(&domainname), category, &msgid, plural) {
+        return translation;
+    }
+    if let Some(translation) = scope::translate(Some(&domainname), category, &msgid, plural) {
+        return translation;
+    }
+    let raw_category = match category.to_raw() {
+        Some(category) => category,
+        None => {
+            missing::report(Some(&domainname), category, &msgid, plural);
+            // Same fallback as dcngettext() uses when there's no translation
+            if n == 1 {
+                return msgid.into_string().unwrap();
+            }
+            return msgid_plural.into_string().unwrap();
+        }
+    };
     unsafe {
-        CStr::from_ptr(ffi::dcngettext(
+        let translation = ffi::dcngettext(
             domainname.as_ptr(),
             msgid.as_ptr(),
             msgid_plural.as_ptr(),
             n as c_ulong,
-            category as i32,
-        ))
-        .to_str()
-        .expect("dcngettext() returned invalid UTF-8")
-        .to_owned()
+            raw_category,
+        );
+        missing::check(translation, Some(&domainname), category, &msgid, plural);
+        CStr::from_ptr(translation)
+            .to_str()
+            .expect("dcngettext() returned invalid UTF-8")
+            .to_owned()
     }
 }
 
@@ -335,8 +499,11 @@ where
 /// underlying C API.
 pub fn textdomain<T: Into<Vec<u8>>>(domainname: T) -> Result<Vec<u8>, io::Error> {
     let domainname = CString::new(domainname).expect("`domainname` contains an internal 0 byte");
+    let _config = GettextConfig::lock();
     unsafe {
         let result = ffi::textdomain(domainname.as_ptr());
+        cache::invalidate();
+        scope::invalidate();
         if result.is_null() {
             Err(io::Error::last_os_error())
         } else {
@@ -365,7 +532,21 @@ where
     U: Into<PathBuf>,
 {
     let domainname = CString::new(domainname).expect("`domainname` contains an internal 0 byte");
-    let dirname = dirname.into().into_os_string();
+    let dirname = dirname.into();
+    let _config = GettextConfig::lock();
+    let result = bind_domain_directory(&domainname, dirname.clone());
+    cache::invalidate();
+    scope::invalidate();
+    let result = result?;
+    reload::record_binding(domainname.as_bytes(), &dirname);
+    Ok(result)
+}
+
+/// `bindtextdomain` without recording the directory for [`reload_catalogs`].
+///
+/// [`reload_catalogs`]: fn.reload_catalogs.html
+fn bind_domain_directory(domainname: &CStr, dirname: PathBuf) -> Result<PathBuf, io::Error> {
+    let dirname = dirname.into_os_string();
 
     #[cfg(windows)]
     {
@@ -418,7 +599,7 @@ where
 ///
 /// Returns an opaque string that describes the locale set. You can pass that string into
 /// `setlocale()` later to set the same local again. `None` means the call failed (the underlying
-/// API doesn't provide any details).
+/// API doesn't provide any details), or that the target doesn't have this `category`.
 ///
 /// For more information, see [setlocale(3)][].
 ///
@@ -430,14 +611,36 @@ where
 /// underlying C API.
 pub fn setlocale<T: Into<Vec<u8>>>(category: LocaleCategory, locale: T) -> Option<Vec<u8>> {
     let c = CString::new(locale).expect("`locale` contains an internal 0 byte");
-    unsafe {
-        let ret = ffi::setlocale(category as i32, c.as_ptr());
+    let raw_category = match category.to_raw() {
+        Some(raw_category) => raw_category,
+        None => {
+            debug!(
+                "setlocale({:?}, {:?}): no such category on this platform",
+                category, c
+            );
+            return None;
+        }
+    };
+    let _config = GettextConfig::lock();
+    let result = unsafe {
+        let ret = ffi::setlocale(raw_category, c.as_ptr());
+        cache::invalidate();
         if ret.is_null() {
             None
         } else {
             Some(CStr::from_ptr(ret).to_bytes().to_owned())
         }
-    }
+    };
+    match result {
+        Some(ref locale) => debug!(
+            "setlocale({:?}, {:?}) switched to {:?}",
+            category,
+            c,
+            String::from_utf8_lossy(locale)
+        ),
+        None => debug!("setlocale({:?}, {:?}) failed", category, c),
+    }
+    result
 }
 
 /// Set encoding of translated messages.
@@ -465,8 +668,10 @@ where
 {
     let domainname = CString::new(domainname).expect("`domainname` contains an internal 0 byte");
     let codeset = CString::new(codeset.into()).expect("`codeset` contains an internal 0 byte");
+    let _config = GettextConfig::lock();
     unsafe {
         let result = ffi::bind_textdomain_codeset(domainname.as_ptr(), codeset.as_ptr());
+        cache::invalidate();
         if result.is_null() {
             let error = io::Error::last_os_error();
             if let Some(0) = error.raw_os_error() {
@@ -517,7 +722,8 @@ where
 
     let translation = gettext(text);
     if translation.contains(CONTEXT_SEPARATOR as char) {
-        return gettext(msgid);
+        // The first lookup has reported the missing translation already, with the context
+        return missing::unreported(|| gettext(msgid));
     }
 
     translation
@@ -548,7 +754,8 @@ where
 
     let translation = ngettext(singular_ctxt, plural_ctxt, n);
     if translation.contains(CONTEXT_SEPARATOR as char) {
-        return ngettext(singular_msgid, plural_msgid, n);
+        // The first lookup has reported the missing translation already, with the context
+        return missing::unreported(|| ngettext(singular_msgid, plural_msgid, n));
     }
 
     translation
@@ -761,4 +968,33 @@ mod tests {
     fn npgettext_panics_on_zero_in_msgid_plural() {
         npgettext("context", "uno", "one \0fewer", 10585);
     }
+
+    #[test]
+    fn locale_category_values() {
+        use LocaleCategory::*;
+
+        let categories = [
+            LcCType,
+            LcNumeric,
+            LcTime,
+            LcCollate,
+            LcMonetary,
+            LcMessages,
+            LcAll,
+            LcPaper,
+            LcName,
+            LcAddress,
+            LcTelephone,
+            LcMeasurement,
+            LcIdentification,
+        ];
+        let values: Vec<c_int> = categories.iter().filter_map(|c| c.to_raw()).collect();
+        for (i, value) in values.iter().enumerate() {
+            assert!(!values[i + 1..].contains(value));
+        }
+
+        // POSIX requires these
+        assert_eq!(LcAll.to_raw(), Some(ffi::LC_ALL));
+        assert_eq!(LcMessages.to_raw(), Some(ffi::LC_MESSAGES));
+    }
 }
@@ -0,0 +1,285 @@
+//! Query the conventions of the current locale, e.g. the decimal point or the names of months.
+//!
+//! Each function reads one locale category, as set with [`setlocale`][::setlocale]. Programs start
+//! in the "C" locale, whose conventions are those of American English without any digit grouping;
+//! call e.g. `setlocale(LocaleCategory::LcAll, "")` to switch to the user's locale.
+
+extern crate gettext_sys as ffi;
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+use super::GettextConfig;
+
+/// How numbers are formatted, according to `LocaleCategory::LcNumeric`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumericInfo {
+    /// Separates the integer part of a number from the fraction, e.g. "," in German.
+    pub decimal_point: String,
+    /// Separates groups of digits in the integer part, e.g. "." in German. Empty if digits aren't
+    /// grouped.
+    pub thousands_separator: String,
+    /// Sizes of the digit groups, starting with the rightmost one, in the format of
+    /// `localeconv()`: the last size repeats, unless it's `CHAR_MAX`, which ends the grouping.
+    pub grouping: Vec<u8>,
+}
+
+/// How amounts of money are formatted, according to `LocaleCategory::LcMonetary`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonetaryInfo {
+    /// Local currency symbol, e.g. "€".
+    pub currency_symbol: String,
+    /// International currency symbol, e.g. "EUR ": the ISO 4217 code followed by a separator.
+    pub international_currency_symbol: String,
+    /// Decimal point for amounts of money.
+    pub decimal_point: String,
+    /// Separates groups of digits in amounts of money.
+    pub thousands_separator: String,
+    /// Sizes of the digit groups in amounts of money; see [`NumericInfo::grouping`].
+    ///
+    /// [`NumericInfo::grouping`]: struct.NumericInfo.html#structfield.grouping
+    pub grouping: Vec<u8>,
+    /// Sign of non-negative amounts, usually empty.
+    pub positive_sign: String,
+    /// Sign of negative amounts, e.g. "-".
+    pub negative_sign: String,
+    /// Number of digits after the decimal point, e.g. 2 for cents. `None` if not specified, as in
+    /// the "C" locale.
+    pub fraction_digits: Option<u8>,
+}
+
+/// Names of days and months, according to `LocaleCategory::LcTime`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeInfo {
+    /// Names of the days of the week, starting with Sunday.
+    pub day_names: [String; 7],
+    /// Abbreviated names of the days of the week, starting with Sunday.
+    pub abbreviated_day_names: [String; 7],
+    /// Names of the months, starting with January.
+    pub month_names: [String; 12],
+    /// Abbreviated names of the months, starting with January.
+    pub abbreviated_month_names: [String; 12],
+}
+
+/// Paper size, according to `LocaleCategory::LcPaper`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaperSize {
+    /// Width in millimetres, e.g. 210 for A4.
+    pub width_mm: u32,
+    /// Height in millimetres, e.g. 297 for A4.
+    pub height_mm: u32,
+}
+
+/// Get the conventions for formatting numbers.
+pub fn numeric() -> NumericInfo {
+    // `localeconv` returns a buffer that `setlocale` overwrites
+    let _config = GettextConfig::lock();
+    unsafe {
+        let lconv = &*ffi::localeconv();
+        NumericInfo {
+            decimal_point: c_string(lconv.decimal_point),
+            thousands_separator: c_string(lconv.thousands_sep),
+            grouping: c_bytes(lconv.grouping),
+        }
+    }
+}
+
+/// Get the conventions for formatting amounts of money.
+pub fn monetary() -> MonetaryInfo {
+    let _config = GettextConfig::lock();
+    unsafe {
+        let lconv = &*ffi::localeconv();
+        MonetaryInfo {
+            currency_symbol: c_string(lconv.currency_symbol),
+            international_currency_symbol: c_string(lconv.int_curr_symbol),
+            decimal_point: c_string(lconv.mon_decimal_point),
+            thousands_separator: c_string(lconv.mon_thousands_sep),
+            grouping: c_bytes(lconv.mon_grouping),
+            positive_sign: c_string(lconv.positive_sign),
+            negative_sign: c_string(lconv.negative_sign),
+            // CHAR_MAX means "not specified"
+            fraction_digits: match lconv.frac_digits {
+                c_char::MAX => None,
+                digits => Some(digits as u8),
+            },
+        }
+    }
+}
+
+/// Get the names of days and months.
+///
+/// Returns `None` on platforms without `nl_langinfo()`, e.g. Windows.
+pub fn time() -> Option<TimeInfo> {
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly"
+    ))]
+    {
+        let _config = GettextConfig::lock();
+        let langinfo = |item| unsafe { c_string(ffi::nl_langinfo(item)) };
+        Some(TimeInfo {
+            day_names: [
+                ffi::DAY_1,
+                ffi::DAY_2,
+                ffi::DAY_3,
+                ffi::DAY_4,
+                ffi::DAY_5,
+                ffi::DAY_6,
+                ffi::DAY_7,
+            ]
+            .map(langinfo),
+            abbreviated_day_names: [
+                ffi::ABDAY_1,
+                ffi::ABDAY_2,
+                ffi::ABDAY_3,
+                ffi::ABDAY_4,
+                ffi::ABDAY_5,
+                ffi::ABDAY_6,
+                ffi::ABDAY_7,
+            ]
+            .map(langinfo),
+            month_names: [
+                ffi::MON_1,
+                ffi::MON_2,
+                ffi::MON_3,
+                ffi::MON_4,
+                ffi::MON_5,
+                ffi::MON_6,
+                ffi::MON_7,
+                ffi::MON_8,
+                ffi::MON_9,
+                ffi::MON_10,
+                ffi::MON_11,
+                ffi::MON_12,
+            ]
+            .map(langinfo),
+            abbreviated_month_names: [
+                ffi::ABMON_1,
+                ffi::ABMON_2,
+                ffi::ABMON_3,
+                ffi::ABMON_4,
+                ffi::ABMON_5,
+                ffi::ABMON_6,
+                ffi::ABMON_7,
+                ffi::ABMON_8,
+                ffi::ABMON_9,
+                ffi::ABMON_10,
+                ffi::ABMON_11,
+                ffi::ABMON_12,
+            ]
+            .map(langinfo),
+        })
+    }
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly"
+    )))]
+    {
+        None
+    }
+}
+
+/// Get the paper size.
+///
+/// Only glibc keeps track of paper sizes; returns `None` elsewhere.
+pub fn paper_size() -> Option<PaperSize> {
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    {
+        // glibc returns these numbers in place of the pointer: its locale data stores each item as
+        // a `union { const char *string; unsigned int word; }`, and `nl_langinfo` returns the
+        // `string` member. This union mirrors that layout to read the `word` member back.
+        #[repr(C)]
+        union Word {
+            string: *mut c_char,
+            word: u32,
+        }
+        debug_assert!(std::mem::size_of::<*mut c_char>() >= std::mem::size_of::<u32>());
+
+        let _config = GettextConfig::lock();
+        let langinfo = |item| unsafe {
+            Word {
+                string: ffi::nl_langinfo(item),
+            }
+            .word
+        };
+        Some(PaperSize {
+            width_mm: langinfo(ffi::_NL_PAPER_WIDTH),
+            height_mm: langinfo(ffi::_NL_PAPER_HEIGHT),
+        })
+    }
+    #[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+    {
+        None
+    }
+}
+
+unsafe fn c_string(s: *const c_char) -> String {
+    if s.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(s).to_string_lossy().into_owned()
+    }
+}
+
+unsafe fn c_bytes(s: *const c_char) -> Vec<u8> {
+    if s.is_null() {
+        vec![]
+    } else {
+        CStr::from_ptr(s).to_bytes().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn c_locale() {
+        let config = GettextConfig::lock();
+        for &category in &[
+            ::LocaleCategory::LcNumeric,
+            ::LocaleCategory::LcMonetary,
+            ::LocaleCategory::LcTime,
+            ::LocaleCategory::LcPaper,
+        ] {
+            config.setlocale(category, "C");
+        }
+
+        assert_eq!(
+            numeric(),
+            NumericInfo {
+                decimal_point: ".".to_owned(),
+                thousands_separator: "".to_owned(),
+                grouping: vec![],
+            }
+        );
+
+        let monetary = monetary();
+        assert_eq!(monetary.currency_symbol, "");
+        assert_eq!(monetary.fraction_digits, None);
+
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        {
+            let time = time().unwrap();
+            assert_eq!(time.day_names[0], "Sunday");
+            assert_eq!(time.abbreviated_day_names[6], "Sat");
+            assert_eq!(time.month_names[0], "January");
+            assert_eq!(time.abbreviated_month_names[11], "Dec");
+        }
+
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        assert_eq!(
+            paper_size(),
+            Some(PaperSize {
+                width_mm: 210,
+                height_mm: 297,
+            })
+        );
+    }
+}
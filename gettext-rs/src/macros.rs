@@ -33,7 +33,8 @@ macro_rules! freplace {
 #[macro_export]
 macro_rules! gettext {
     ($msgid:expr, $($args:expr),+ $(,)?) => {{
-        let format = $crate::gettext($msgid);
+        let msgid = $msgid;
+        let format = $crate::__with_location(file!(), line!(), || $crate::gettext(msgid));
         $crate::freplace!(format, $($args),*)
     }};
 }
@@ -47,7 +48,9 @@ macro_rules! gettext {
 #[macro_export]
 macro_rules! dgettext {
     ($domainname:expr, $msgid:expr, $($args:expr),+ $(,)?) => {{
-        let format = $crate::dgettext($domainname, $msgid);
+        let domainname = $domainname;
+        let msgid = $msgid;
+        let format = $crate::__with_location(file!(), line!(), || $crate::dgettext(domainname, msgid));
         $crate::freplace!(format, $($args),*)
     }};
 }
@@ -61,7 +64,10 @@ macro_rules! dgettext {
 #[macro_export]
 macro_rules! dcgettext {
     ($domainname:expr, $category:expr, $msgid:expr, $($args:expr),+ $(,)?) => {{
-        let format = $crate::dcgettext($domainname, $msgid, $category);
+        let domainname = $domainname;
+        let msgid = $msgid;
+        let category = $category;
+        let format = $crate::__with_location(file!(), line!(), || $crate::dcgettext(domainname, msgid, category));
         $crate::freplace!(format, $($args),*)
     }};
 }
@@ -75,7 +81,10 @@ macro_rules! dcgettext {
 #[macro_export]
 macro_rules! ngettext {
     ($msgid:expr, $msgid_plural:expr, $n:expr, $($args:expr),+ $(,)?) => {{
-        let format = $crate::ngettext($msgid, $msgid_plural, $n);
+        let msgid = $msgid;
+        let msgid_plural = $msgid_plural;
+        let n = $n;
+        let format = $crate::__with_location(file!(), line!(), || $crate::ngettext(msgid, msgid_plural, n));
         $crate::freplace!(format, $($args),*)
     }}
 }
@@ -89,7 +98,11 @@ macro_rules! ngettext {
 #[macro_export]
 macro_rules! dngettext {
     ($domainname:expr, $msgid:expr, $msgid_plural:expr, $n:expr, $($args:expr),+ $(,)?) => {{
-        let format = $crate::dngettext($domainname, $msgid, $msgid_plural, $n);
+        let domainname = $domainname;
+        let msgid = $msgid;
+        let msgid_plural = $msgid_plural;
+        let n = $n;
+        let format = $crate::__with_location(file!(), line!(), || $crate::dngettext(domainname, msgid, msgid_plural, n));
         $crate::freplace!(format, $($args),*)
     }}
 }
@@ -103,7 +116,12 @@ macro_rules! dngettext {
 #[macro_export]
 macro_rules! dcngettext {
     ($domainname:expr, $category:expr, $msgid:expr, $msgid_plural:expr, $n:expr, $($args:expr),+ $(,)?) => {{
-        let format = $crate::dcngettext($domainname, $msgid, $msgid_plural, $n, $category);
+        let domainname = $domainname;
+        let msgid = $msgid;
+        let msgid_plural = $msgid_plural;
+        let n = $n;
+        let category = $category;
+        let format = $crate::__with_location(file!(), line!(), || $crate::dcngettext(domainname, msgid, msgid_plural, n, category));
         $crate::freplace!(format, $($args),*)
     }}
 }
@@ -117,7 +135,9 @@ macro_rules! dcngettext {
 #[macro_export]
 macro_rules! pgettext {
     ($msgctxt:expr, $msgid:expr, $($args:expr),+ $(,)?) => {{
-        let format = $crate::pgettext($msgctxt, $msgid);
+        let msgctxt = $msgctxt;
+        let msgid = $msgid;
+        let format = $crate::__with_location(file!(), line!(), || $crate::pgettext(msgctxt, msgid));
         $crate::freplace!(format, $($args),*)
     }}
 }
@@ -131,7 +151,11 @@ macro_rules! pgettext {
 #[macro_export]
 macro_rules! npgettext {
     ($msgctxt:expr, $msgid:expr, $msgid_plural:expr, $n:expr, $($args:expr),+ $(,)?) => {{
-        let format = $crate::npgettext($msgctxt, $msgid, $msgid_plural, $n);
+        let msgctxt = $msgctxt;
+        let msgid = $msgid;
+        let msgid_plural = $msgid_plural;
+        let n = $n;
+        let format = $crate::__with_location(file!(), line!(), || $crate::npgettext(msgctxt, msgid, msgid_plural, n));
         $crate::freplace!(format, $($args),*)
     }}
 }
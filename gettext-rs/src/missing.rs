@@ -0,0 +1,328 @@
+//! Reporting lookups that found no translation.
+
+extern crate gettext_sys as ffi;
+
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+use super::LocaleCategory;
+
+type Hook = Arc<dyn Fn(&MissingTranslation) + Send + Sync>;
+
+static HOOK: Mutex<Option<Hook>> = Mutex::new(None);
+
+thread_local! {
+    /// Where the lookup macro that is running on this thread was called, if any.
+    static LOCATION: Cell<Option<SourceLocation>> = const { Cell::new(None) };
+    /// Whether the hook is running on this thread, so that its own lookups aren't reported.
+    static REPORTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A lookup that found no translation, and returned the original message instead.
+///
+/// See [`set_missing_translation_hook`].
+///
+/// [`set_missing_translation_hook`]: fn.set_missing_translation_hook.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingTranslation<'a> {
+    /// Text domain the message was looked up in.
+    pub domain: &'a str,
+    /// Locale category the message was looked up for; `LcMessages` unless it was looked up with
+    /// [`dcgettext`][fn@::dcgettext] or [`dcngettext`][fn@::dcngettext].
+    pub category: LocaleCategory,
+    /// Context of the message, if it was looked up with [`pgettext`][fn@::pgettext] or
+    /// [`npgettext`][fn@::npgettext].
+    pub context: Option<&'a str>,
+    /// The message.
+    pub msgid: &'a str,
+    /// Plural form of the message, if it was looked up with one of the `n*gettext` functions.
+    pub msgid_plural: Option<&'a str>,
+    /// The number the plural form was chosen for.
+    pub n: Option<u32>,
+    /// Where the lookup happened, if it was done with one of the macros, e.g. [`gettext!`].
+    ///
+    /// [`gettext!`]: macro.gettext.html
+    pub location: Option<SourceLocation>,
+}
+
+/// Place in the source code, as given by the `file!()` and `line!()` macros.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// Path of the source file.
+    pub file: &'static str,
+    /// Line in that file, starting at 1.
+    pub line: u32,
+}
+
+/// Call `hook` whenever a lookup finds no translation.
+///
+/// [`gettext`][fn@::gettext] and the other lookup functions return the message untranslated if the
+/// catalog doesn't have it. With a hook, such lookups can be noticed, e.g. to collect the messages
+/// that still need translating. The hook is called on the thread that did the lookup, with all the
+/// information needed to write the message to a POT file.
+///
+/// Lookups of the empty message (which is used to read catalog headers) and lookups done by the
+/// hook itself aren't reported. Neither are lookups while pseudo-localization is enabled.
+///
+/// Note that libintl doesn't load catalogs for the C and POSIX locales, so in those locales every
+/// lookup is reported.
+///
+/// Replaces the previous hook, if any.
+///
+/// # Examples
+///
+/// ```no_run
+/// use gettextrs::*;
+///
+/// set_missing_translation_hook(|missing| {
+///     if let Some(location) = missing.location {
+///         eprintln!("{}:{}: untranslated", location.file, location.line);
+///     }
+///     eprintln!("msgid {:?}", missing.msgid);
+/// });
+/// ```
+pub fn set_missing_translation_hook<F>(hook: F)
+where
+    F: Fn(&MissingTranslation) + Send + Sync + 'static,
+{
+    *HOOK.lock().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(hook));
+}
+
+/// Stop reporting missing translations, removing the hook set by
+/// [`set_missing_translation_hook`].
+///
+/// [`set_missing_translation_hook`]: fn.set_missing_translation_hook.html
+pub fn clear_missing_translation_hook() {
+    *HOOK.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// This is an implementation detail of the gettext macros. Don't call this directly.
+#[doc(hidden)]
+pub fn with_location<T, F: FnOnce() -> T>(file: &'static str, line: u32, lookup: F) -> T {
+    struct Restore(Option<SourceLocation>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            LOCATION.with(|location| location.set(self.0));
+        }
+    }
+
+    let location = SourceLocation { file, line };
+    let _restore = Restore(LOCATION.with(|current| current.replace(Some(location))));
+    lookup()
+}
+
+/// Report a missing translation if libintl returned one of the untranslated messages.
+pub(crate) fn check(
+    translation: *const c_char,
+    domainname: Option<&CStr>,
+    category: LocaleCategory,
+    msgid: &CStr,
+    plural: Option<(&CStr, u32)>,
+) {
+    let untranslated = translation == msgid.as_ptr()
+        || plural.is_some_and(|(msgid_plural, _)| translation == msgid_plural.as_ptr());
+    if untranslated {
+        report(domainname, category, msgid, plural);
+    }
+}
+
+/// Restores `REPORTING` once the hook or the unreported lookup returns (or panics).
+struct Reporting(bool);
+
+impl Drop for Reporting {
+    fn drop(&mut self) {
+        REPORTING.with(|reporting| reporting.set(self.0));
+    }
+}
+
+/// Do `lookup` without reporting missing translations, e.g. because the miss was reported by an
+/// earlier lookup of the same message.
+pub(crate) fn unreported<T, F: FnOnce() -> T>(lookup: F) -> T {
+    let _reporting = Reporting(REPORTING.with(|reporting| reporting.replace(true)));
+    lookup()
+}
+
+/// Pass a lookup that found no translation to the hook, if there is one.
+pub(crate) fn report(
+    domainname: Option<&CStr>,
+    category: LocaleCategory,
+    msgid: &CStr,
+    plural: Option<(&CStr, u32)>,
+) {
+    if msgid.to_bytes().is_empty() {
+        return;
+    }
+    trace!(
+        "no translation for {:?} (plural {:?}) in domain {:?}, category {:?}",
+        msgid,
+        plural,
+        domainname,
+        category
+    );
+    if REPORTING.with(Cell::get) {
+        return;
+    }
+    let hook = match *HOOK.lock().unwrap_or_else(|e| e.into_inner()) {
+        Some(ref hook) => Arc::clone(hook),
+        None => return,
+    };
+
+    let domain = match domainname {
+        Some(domainname) => domainname.to_string_lossy(),
+        None => unsafe {
+            let current = ffi::textdomain(ptr::null());
+            if current.is_null() {
+                Cow::Borrowed("messages")
+            } else {
+                Cow::Owned(CStr::from_ptr(current).to_string_lossy().into_owned())
+            }
+        },
+    };
+    // These come from `String`s, so they are valid UTF-8
+    let msgid = msgid.to_str().unwrap();
+    // `pgettext` and `npgettext` look up the context and the message joined by EOT
+    let (context, msgid) = match msgid.find('\x04') {
+        Some(eot) => (Some(&msgid[..eot]), &msgid[eot + 1..]),
+        None => (None, msgid),
+    };
+    let msgid_plural = plural.map(|(msgid_plural, _)| {
+        let msgid_plural = msgid_plural.to_str().unwrap();
+        match context {
+            Some(context) => msgid_plural
+                .strip_prefix(context)
+                .and_then(|rest| rest.strip_prefix('\x04'))
+                .unwrap_or(msgid_plural),
+            None => msgid_plural,
+        }
+    });
+    let missing = MissingTranslation {
+        domain: &domain,
+        category,
+        context,
+        msgid,
+        msgid_plural,
+        n: plural.map(|(_, n)| n),
+        location: LOCATION.with(Cell::get),
+    };
+
+    let _reporting = Reporting(REPORTING.with(|reporting| reporting.replace(true)));
+    hook(&missing);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Reported {
+        domain: String,
+        context: Option<String>,
+        msgid: String,
+        msgid_plural: Option<String>,
+        n: Option<u32>,
+        location: Option<SourceLocation>,
+    }
+
+    // The hook is global, so all the checks that need it are in this one test
+    #[test]
+    fn hook() {
+        // Keeps other tests from changing the default domain, which `pgettext` looks up in
+        let _config = ::GettextConfig::lock();
+        let default_domain = String::from_utf8(::getters::current_textdomain().unwrap()).unwrap();
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let sink = reported.clone();
+        set_missing_translation_hook(move |missing| {
+            // Other tests do lookups, too
+            if missing.domain != "missing_test" && !missing.msgid.starts_with("missing_test ") {
+                return;
+            }
+            // Not reported, even though there is no translation
+            assert_eq!(::dgettext("missing_test", "From the hook"), "From the hook");
+            sink.lock().unwrap().push(Reported {
+                domain: missing.domain.to_owned(),
+                context: missing.context.map(str::to_owned),
+                msgid: missing.msgid.to_owned(),
+                msgid_plural: missing.msgid_plural.map(str::to_owned),
+                n: missing.n,
+                location: missing.location,
+            });
+        });
+
+        ::dgettext("missing_test", "Hello");
+        ::dgettext("missing_test", "");
+        ::dngettext("missing_test", "One file", "{} files", 2);
+        ::dcgettext("missing_test", "menu\x04Open", LocaleCategory::LcTime);
+        // The message is looked up again without the context, which mustn't be reported
+        ::pgettext("menu", "missing_test Save");
+        ::npgettext("inbox", "missing_test One message", "{} messages", 3);
+        let line = line!() + 1;
+        ::dgettext!("missing_test", "Hello, {}", "world");
+        clear_missing_translation_hook();
+        ::dgettext("missing_test", "After");
+
+        let location = SourceLocation {
+            file: file!(),
+            line,
+        };
+        assert_eq!(
+            *reported.lock().unwrap(),
+            vec![
+                Reported {
+                    domain: "missing_test".to_owned(),
+                    context: None,
+                    msgid: "Hello".to_owned(),
+                    msgid_plural: None,
+                    n: None,
+                    location: None,
+                },
+                Reported {
+                    domain: "missing_test".to_owned(),
+                    context: None,
+                    msgid: "One file".to_owned(),
+                    msgid_plural: Some("{} files".to_owned()),
+                    n: Some(2),
+                    location: None,
+                },
+                Reported {
+                    domain: "missing_test".to_owned(),
+                    context: Some("menu".to_owned()),
+                    msgid: "Open".to_owned(),
+                    msgid_plural: None,
+                    n: None,
+                    location: None,
+                },
+                Reported {
+                    domain: default_domain.clone(),
+                    context: Some("menu".to_owned()),
+                    msgid: "missing_test Save".to_owned(),
+                    msgid_plural: None,
+                    n: None,
+                    location: None,
+                },
+                Reported {
+                    domain: default_domain.clone(),
+                    context: Some("inbox".to_owned()),
+                    msgid: "missing_test One message".to_owned(),
+                    msgid_plural: Some("{} messages".to_owned()),
+                    n: Some(3),
+                    location: None,
+                },
+                Reported {
+                    domain: "missing_test".to_owned(),
+                    context: None,
+                    msgid: "Hello, {}".to_owned(),
+                    msgid_plural: None,
+                    n: None,
+                    location: Some(location),
+                },
+            ]
+        );
+        assert_eq!(LOCATION.with(Cell::get), None);
+    }
+}
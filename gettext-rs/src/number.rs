@@ -0,0 +1,164 @@
+//! Formatting numbers the way the `LC_NUMERIC` category of the current locale says to.
+
+use std::fmt;
+use std::os::raw::c_char;
+
+use super::locale_info::{self, NumericInfo};
+
+/// A number that is displayed with the decimal point and the digit grouping of the current locale.
+///
+/// Rust formats numbers the same way regardless of the locale, e.g. `12345.5` is always
+/// "12345.5". Wrapped in `LocalizedNumber`, it becomes "12.345,5" in a German locale, "12 345,5"
+/// in a French one, and "12,345.5" in an American one. This is meant for the arguments of
+/// [`gettext!`] and the other macros:
+///
+/// ```no_run
+/// use gettextrs::*;
+///
+/// setlocale(LocaleCategory::LcNumeric, "");
+/// let deleted = 12345;
+/// println!("{}", gettext!("{} files deleted", LocalizedNumber(deleted)));
+/// ```
+///
+/// The conventions are looked up whenever the number is displayed, and come from the
+/// `LocaleCategory::LcNumeric` category (see [`locale_info::numeric`]). Note that programs start
+/// in the "C" locale, whose conventions are the same as Rust's; call [`setlocale`] to switch to
+/// the user's locale. (The [`TextDomain`] builder only changes `LcMessages` by default.)
+///
+/// Precision, width and sign flags are honoured, e.g. `{:+.2}`; zero padding goes in front of the
+/// grouped digits. Integers and floats of all sizes are supported.
+///
+/// [`gettext!`]: macro.gettext.html
+/// [`locale_info::numeric`]: locale_info/fn.numeric.html
+/// [`setlocale`]: fn.setlocale.html
+/// [`TextDomain`]: struct.TextDomain.html
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct LocalizedNumber<T>(pub T);
+
+macro_rules! localized_number_display {
+    ($($t:ty)*) => {$(
+        impl fmt::Display for LocalizedNumber<$t> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let plain = match f.precision() {
+                    Some(precision) => format!("{:.*}", precision, self.0),
+                    None => self.0.to_string(),
+                };
+                let localized = locale_info::numeric().localize(&plain);
+                match localized.strip_prefix('-') {
+                    Some(magnitude) => f.pad_integral(false, "", magnitude),
+                    None => f.pad_integral(true, "", &localized),
+                }
+            }
+        }
+    )*};
+}
+
+localized_number_display!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize f32 f64);
+
+impl NumericInfo {
+    /// Apply these conventions to a number formatted by Rust, e.g. "-12345.5".
+    fn localize(&self, plain: &str) -> String {
+        let (sign, magnitude) = match plain.strip_prefix('-') {
+            Some(magnitude) => ("-", magnitude),
+            None => ("", plain),
+        };
+        // "inf" and "NaN" are left alone
+        if !magnitude.starts_with(|c: char| c.is_ascii_digit()) {
+            return plain.to_owned();
+        }
+        let (integer, fraction) = match magnitude.find('.') {
+            Some(point) => (&magnitude[..point], Some(&magnitude[point + 1..])),
+            None => (magnitude, None),
+        };
+
+        let mut localized = sign.to_owned();
+        localized += &self.group(integer);
+        if let Some(fraction) = fraction {
+            localized += &self.decimal_point;
+            localized += fraction;
+        }
+        localized
+    }
+
+    /// Insert the thousands separator between the groups of `digits`.
+    fn group(&self, digits: &str) -> String {
+        let mut groups = vec![];
+        let mut rest = digits;
+        let mut sizes = self.grouping.iter();
+        let mut size = 0;
+        if !self.thousands_separator.is_empty() {
+            loop {
+                match sizes.next() {
+                    // 0 repeats the previous size; so does the end of the list
+                    Some(0) | None => {}
+                    // CHAR_MAX (or anything negative) means no more grouping
+                    Some(&next) if next >= c_char::MAX as u8 => break,
+                    Some(&next) => size = usize::from(next),
+                }
+                if size == 0 || size >= rest.len() {
+                    break;
+                }
+                let (left, group) = rest.split_at(rest.len() - size);
+                groups.push(group);
+                rest = left;
+            }
+        }
+        groups.push(rest);
+        groups.reverse();
+        groups.join(&self.thousands_separator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conventions(decimal_point: &str, thousands_sep: &str, grouping: &[u8]) -> NumericInfo {
+        NumericInfo {
+            decimal_point: decimal_point.to_owned(),
+            thousands_separator: thousands_sep.to_owned(),
+            grouping: grouping.to_vec(),
+        }
+    }
+
+    #[test]
+    fn localize() {
+        let german = conventions(",", ".", &[3, 3]);
+        assert_eq!(german.localize("12345"), "12.345");
+        assert_eq!(german.localize("-1234567.25"), "-1.234.567,25");
+        assert_eq!(german.localize("123"), "123");
+        assert_eq!(german.localize("0.5"), "0,5");
+        assert_eq!(german.localize("inf"), "inf");
+        assert_eq!(german.localize("-inf"), "-inf");
+        assert_eq!(german.localize("NaN"), "NaN");
+
+        let indian = conventions(".", ",", &[3, 2]);
+        assert_eq!(indian.localize("1234567890"), "1,23,45,67,890");
+
+        // Only the first group is separated
+        let limited = conventions(".", " ", &[3, c_char::MAX as u8]);
+        assert_eq!(limited.localize("1234567"), "1234 567");
+
+        let c = conventions(".", "", &[]);
+        assert_eq!(c.localize("-1234567.25"), "-1234567.25");
+        let no_grouping = conventions(".", ",", &[]);
+        assert_eq!(no_grouping.localize("1234567"), "1234567");
+    }
+
+    #[test]
+    fn display() {
+        let _config = ::GettextConfig::lock();
+        ::setlocale(::LocaleCategory::LcNumeric, "C");
+
+        assert_eq!(LocalizedNumber(12345).to_string(), "12345");
+        assert_eq!(LocalizedNumber(-2.5f64).to_string(), "-2.5");
+        assert_eq!(format!("{:.2}", LocalizedNumber(1.0f32)), "1.00");
+        assert_eq!(format!("{:+}", LocalizedNumber(7u8)), "+7");
+        assert_eq!(format!("{:>6}", LocalizedNumber(-42i64)), "   -42");
+        assert_eq!(format!("{:06}", LocalizedNumber(-42i64)), "-00042");
+        assert_eq!(
+            ::gettext!("{} files deleted", LocalizedNumber(3)),
+            "3 files deleted"
+        );
+    }
+}
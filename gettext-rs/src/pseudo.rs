@@ -0,0 +1,242 @@
+//! Pseudo-localization: replacing translations with a distorted version of the original message.
+
+use std::env;
+use std::ffi::CStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use super::cache;
+
+/// Environment variable that enables pseudo-localization when the program starts.
+const ENV_VAR: &str = "GETTEXTRS_PSEUDOLOCALIZATION";
+
+/// Length expansion used if the environment variable doesn't specify one.
+const DEFAULT_EXPANSION: u32 = 30;
+
+/// `STATE` before the environment variable was read.
+const UNKNOWN: u32 = u32::MAX;
+/// `STATE` when pseudo-localization is disabled.
+const DISABLED: u32 = u32::MAX - 1;
+
+/// The length expansion in percent, if pseudo-localization is enabled.
+static STATE: AtomicU32 = AtomicU32::new(UNKNOWN);
+
+/// Make all lookups return pseudo-localized messages instead of translations.
+///
+/// Pseudo-localization helps to find strings that aren't marked for translation, and layouts that
+/// don't leave room for languages with longer words. When it's enabled, [`gettext`][fn@::gettext],
+/// [`ngettext`][fn@::ngettext], [`pgettext`][fn@::pgettext] and all the other lookup functions and
+/// macros of this crate ignore the catalogs and return the original message transformed with
+/// [`pseudolocalize`]: "Hello, {}!" becomes "[Ĥéļļó, {}!~~~]". Text that is displayed
+/// untransformed didn't go through gettext. Catalog headers, i.e. lookups of the empty message,
+/// still come from the catalogs.
+///
+/// `expansion` is the percentage by which the messages are made longer; 30 to 40 is typical for
+/// translations from English.
+///
+/// Pseudo-localization can also be enabled without changing the code, by setting the
+/// `GETTEXTRS_PSEUDOLOCALIZATION` environment variable to the expansion, e.g. "30". (Any other
+/// non-empty value enables it with 30% expansion.)
+///
+/// [`pseudolocalize`]: fn.pseudolocalize.html
+pub fn enable_pseudolocalization(expansion: u32) {
+    STATE.store(expansion.min(DISABLED - 1), Ordering::Relaxed);
+    cache::invalidate();
+}
+
+/// Make lookups return translations again, undoing [`enable_pseudolocalization`] and the
+/// `GETTEXTRS_PSEUDOLOCALIZATION` environment variable.
+///
+/// [`enable_pseudolocalization`]: fn.enable_pseudolocalization.html
+pub fn disable_pseudolocalization() {
+    STATE.store(DISABLED, Ordering::Relaxed);
+    cache::invalidate();
+}
+
+/// The length expansion, if pseudo-localization is enabled.
+fn expansion() -> Option<u32> {
+    let mut state = STATE.load(Ordering::Relaxed);
+    if state == UNKNOWN {
+        let from_env = match env::var(ENV_VAR) {
+            Ok(ref value) if value.is_empty() => DISABLED,
+            Ok(value) => value.trim().parse().unwrap_or(DEFAULT_EXPANSION),
+            Err(_) => DISABLED,
+        };
+        // The API takes precedence if it was called in the meantime
+        state =
+            match STATE.compare_exchange(UNKNOWN, from_env, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => from_env,
+                Err(current) => current,
+            };
+    }
+    if state == DISABLED {
+        None
+    } else {
+        Some(state)
+    }
+}
+
+/// Pseudo-localize a message, if pseudo-localization is enabled.
+///
+/// Returns `None` if it's disabled, so that the caller can look the message up instead. Otherwise,
+/// returns the pseudo-localized `msgid`, or `msgid_plural` if `n` isn't 1. The empty message is
+/// left to the caller as well, since it's used to read catalog headers.
+pub(crate) fn translate(msgid: &CStr, plural: Option<(&CStr, u32)>) -> Option<String> {
+    if msgid.to_bytes().is_empty() {
+        return None;
+    }
+    expansion().map(|expansion| pseudolocalize_lookup(expansion, msgid, plural))
+}
+
+fn pseudolocalize_lookup(expansion: u32, msgid: &CStr, plural: Option<(&CStr, u32)>) -> String {
+    let message = match plural {
+        Some((msgid_plural, n)) if n != 1 => msgid_plural,
+        _ => msgid,
+    };
+    // This comes from a `String`, so it is valid UTF-8
+    pseudolocalize(message.to_str().unwrap(), expansion)
+}
+
+/// Transform a message the way pseudo-localization does.
+///
+/// The letters get accents, the message is made longer by `expansion` percent (with tildes), and
+/// it's wrapped in square brackets, so that truncated text stands out. Placeholders like `{}` and
+/// `%s` are left alone, so formatting still works. The empty message (which is used to look up
+/// catalog headers) is returned as is.
+///
+/// # Examples
+///
+/// ```
+/// use gettextrs::pseudolocalize;
+///
+/// assert_eq!(pseudolocalize("Hello, {}!", 40), "[Ĥéļļó, {}!~~~~]");
+/// ```
+pub fn pseudolocalize(message: &str, expansion: u32) -> String {
+    if message.is_empty() {
+        return String::new();
+    }
+
+    let length = message.chars().count();
+    let padding = (length * expansion as usize).div_ceil(100);
+
+    let mut result = String::with_capacity(message.len() * 2 + padding + 2);
+    result.push('[');
+    let mut chars = message.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                result.push(c);
+                for c in chars.by_ref() {
+                    result.push(c);
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            '%' => {
+                result.push(c);
+                result.extend(chars.next());
+            }
+            _ => result.push(accented(c)),
+        }
+    }
+    result.extend((0..padding).map(|_| '~'));
+    result.push(']');
+    result
+}
+
+fn accented(c: char) -> char {
+    match c {
+        'A' => 'Å',
+        'B' => 'Ɓ',
+        'C' => 'Ç',
+        'D' => 'Đ',
+        'E' => 'É',
+        'F' => 'Ƒ',
+        'G' => 'Ĝ',
+        'H' => 'Ĥ',
+        'I' => 'Î',
+        'J' => 'Ĵ',
+        'K' => 'Ķ',
+        'L' => 'Ļ',
+        'N' => 'Ñ',
+        'O' => 'Ó',
+        'R' => 'Ŕ',
+        'S' => 'Š',
+        'T' => 'Ţ',
+        'U' => 'Û',
+        'W' => 'Ŵ',
+        'Y' => 'Ý',
+        'Z' => 'Ž',
+        'a' => 'á',
+        'b' => 'ƀ',
+        'c' => 'ç',
+        'd' => 'ð',
+        'e' => 'é',
+        'f' => 'ƒ',
+        'g' => 'ĝ',
+        'h' => 'ĥ',
+        'i' => 'î',
+        'j' => 'ĵ',
+        'k' => 'ķ',
+        'l' => 'ļ',
+        'n' => 'ñ',
+        'o' => 'ó',
+        'r' => 'ŕ',
+        's' => 'š',
+        't' => 'ţ',
+        'u' => 'û',
+        'w' => 'ŵ',
+        'y' => 'ý',
+        'z' => 'ž',
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn transform() {
+        assert_eq!(pseudolocalize("Hello", 0), "[Ĥéļļó]");
+        assert_eq!(pseudolocalize("Hello", 30), "[Ĥéļļó~~]");
+        assert_eq!(pseudolocalize("Hello", 100), "[Ĥéļļó~~~~~]");
+        assert_eq!(pseudolocalize("", 30), "");
+
+        // Contexts are kept intact, so that `pgettext` notices there's no translation
+        assert!(pseudolocalize("context\u{4}Open", 30).contains('\u{4}'));
+    }
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(
+            pseudolocalize("{} of {name}: 100%s {", 0),
+            "[{} óƒ {name}: 100%s {]"
+        );
+
+        let format = pseudolocalize("{}, you have {} new messages", 0);
+        assert_eq!(
+            ::freplace!(format, "Ana", 3),
+            "[Ana, ýóû ĥávé 3 ñéŵ méššáĝéš]"
+        );
+    }
+
+    #[test]
+    fn plural_forms() {
+        let msgid = CString::new("One file").unwrap();
+        let msgid_plural = CString::new("{} files").unwrap();
+        assert_eq!(
+            pseudolocalize_lookup(100, &msgid, None),
+            "[Óñé ƒîļé~~~~~~~~]"
+        );
+        assert_eq!(
+            pseudolocalize_lookup(0, &msgid, Some((&msgid_plural, 1))),
+            "[Óñé ƒîļé]"
+        );
+        assert_eq!(
+            pseudolocalize_lookup(0, &msgid, Some((&msgid_plural, 2))),
+            "[{} ƒîļéš]"
+        );
+    }
+}
@@ -0,0 +1,313 @@
+//! Picking up changes to message catalogs while the program runs.
+//!
+//! libintl loads each MO file once, and keeps using it until the program exits. It does, however,
+//! key the loaded files by their full path, so binding a domain to a different spelling of the
+//! same directory (e.g. `/usr/share/locale/.`) makes it read the catalogs afresh.
+
+use std::ffi::CString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use super::{bind_domain_directory, cache, getters, scope, GettextConfig};
+
+/// A domain bound with [`bindtextdomain`][::bindtextdomain].
+struct Binding {
+    domainname: Vec<u8>,
+    dirname: PathBuf,
+    /// How many times the domain was re-bound. Every reload needs a spelling of the directory that
+    /// libintl hasn't seen yet, so this is never reset.
+    reloads: usize,
+}
+
+/// How many times a domain is re-bound at most. Each reload makes the directory two bytes longer
+/// and keeps the catalogs that libintl loaded before in memory, so this can't go on forever.
+const MAX_RELOADS: usize = 100;
+
+impl Binding {
+    /// The next spelling of the directory to re-bind the domain to, or `None` if the domain was
+    /// reloaded [`MAX_RELOADS`] times already.
+    fn next_dirname(&mut self) -> Option<PathBuf> {
+        if self.reloads == MAX_RELOADS {
+            return None;
+        }
+        self.reloads += 1;
+        let mut dirname = self.dirname.clone();
+        for _ in 0..self.reloads {
+            dirname.push(".");
+        }
+        Some(dirname)
+    }
+}
+
+static BINDINGS: Mutex<Vec<Binding>> = Mutex::new(Vec::new());
+
+fn bindings() -> std::sync::MutexGuard<'static, Vec<Binding>> {
+    BINDINGS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Remember the directory of a domain, so that [`reload_catalogs`] can re-bind it later.
+pub(crate) fn record_binding(domainname: &[u8], dirname: &Path) {
+    let mut bindings = bindings();
+    match bindings.iter_mut().find(|b| b.domainname == domainname) {
+        Some(binding) => binding.dirname = dirname.to_owned(),
+        None => bindings.push(Binding {
+            domainname: domainname.to_owned(),
+            dirname: dirname.to_owned(),
+            reloads: 0,
+        }),
+    }
+}
+
+/// Make libintl (and [`with_language`]) read message catalogs from disk again.
+///
+/// Translations that were looked up before this call might come from MO files that have since been
+/// changed or replaced; after this call, they come from the files that are on disk now. This is
+/// meant for development, e.g. to let translators check their work without restarting the program.
+///
+/// This affects the current text domain, and all domains bound with
+/// [`bindtextdomain`][::bindtextdomain] (including the ones bound by [`TextDomain`]). Domains bound
+/// directly through the C API, e.g. by a C library, are left alone.
+///
+/// libintl can't unload catalogs, so every call leaks the ones that were loaded before, and each
+/// domain is only re-bound for the first 100 calls. After that, libintl keeps the catalogs it has
+/// (and a warning is logged if the "log" feature is enabled), though [`with_language`] still picks
+/// up changes.
+///
+/// [`TextDomain`]: struct.TextDomain.html
+/// [`with_language`]: fn.with_language.html
+pub fn reload_catalogs() {
+    let _config = GettextConfig::lock();
+    if let Ok(domainname) = getters::current_textdomain() {
+        let bound = bindings().iter().any(|b| b.domainname == domainname);
+        if !bound {
+            if let Ok(dirname) = getters::domain_directory(domainname.clone()) {
+                record_binding(&domainname, &dirname);
+            }
+        }
+    }
+
+    for binding in bindings().iter_mut() {
+        let domainname = match CString::new(binding.domainname.clone()) {
+            Ok(domainname) => domainname,
+            Err(_) => continue,
+        };
+        match binding.next_dirname() {
+            Some(dirname) => {
+                let _ = bind_domain_directory(&domainname, dirname);
+            }
+            None => warn!(
+                "not reloading catalogs of domain {:?} any more: reloaded {} times already",
+                domainname, MAX_RELOADS
+            ),
+        }
+    }
+
+    // Re-binding already bumps libintl's catalog counter, which drops the translations it has
+    // cached, so there is no need to touch the counter here
+    scope::clear_catalogs();
+    cache::invalidate();
+}
+
+/// Watch the MO files of bound domains, and reload them when they change.
+///
+/// Every `interval`, this checks the modification times of the MO files of all domains that
+/// [`reload_catalogs`] would reload. If any file was created, changed, or removed, it calls
+/// [`reload_catalogs`] and then `on_reload`, e.g. to redraw the UI.
+///
+/// The files are checked on a background thread until the returned [`CatalogWatcher`] is dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use gettextrs::*;
+/// use std::time::Duration;
+///
+/// TextDomain::new("my_textdomain").init().unwrap();
+///
+/// let _watcher = watch_catalogs(Duration::from_secs(1), || println!("Translations changed"));
+/// ```
+///
+/// [`reload_catalogs`]: fn.reload_catalogs.html
+/// [`CatalogWatcher`]: struct.CatalogWatcher.html
+pub fn watch_catalogs<F>(interval: Duration, mut on_reload: F) -> CatalogWatcher
+where
+    F: FnMut() + Send + 'static,
+{
+    let (stop, stopped) = mpsc::channel::<()>();
+    let thread = thread::spawn(move || {
+        let mut catalogs = catalog_times();
+        while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+            let current = catalog_times();
+            if current != catalogs {
+                reload_catalogs();
+                on_reload();
+                catalogs = current;
+            }
+        }
+    });
+    CatalogWatcher {
+        stop: Some(stop),
+        thread: Some(thread),
+    }
+}
+
+/// Background thread started by [`watch_catalogs`]. Stops when dropped.
+///
+/// Dropping the watcher waits for the thread to finish the check it might be doing, unless the
+/// dropping thread holds the [`GettextConfig`] lock, which the check needs. In that case the
+/// thread is left to finish the check on its own, and might still reload the catalogs and call
+/// `on_reload` after the drop returns.
+///
+/// [`GettextConfig`]: struct.GettextConfig.html
+/// [`watch_catalogs`]: fn.watch_catalogs.html
+#[derive(Debug)]
+pub struct CatalogWatcher {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for CatalogWatcher {
+    fn drop(&mut self) {
+        // Disconnecting the channel wakes the thread up
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            // The thread might be waiting for the lock, and would never finish
+            if !GettextConfig::is_held() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+/// Modification times of the MO files of all bound domains, sorted by path.
+fn catalog_times() -> Vec<(PathBuf, SystemTime)> {
+    let mut domains: Vec<(PathBuf, String)> = bindings()
+        .iter()
+        .map(|b| {
+            let filename = format!("{}.mo", String::from_utf8_lossy(&b.domainname));
+            (b.dirname.clone(), filename)
+        })
+        .collect();
+    if let Ok(domainname) = getters::current_textdomain() {
+        if let Ok(dirname) = getters::domain_directory(domainname.clone()) {
+            let filename = format!("{}.mo", String::from_utf8_lossy(&domainname));
+            domains.push((dirname, filename));
+        }
+    }
+
+    let mut times = Vec::new();
+    for (dirname, filename) in domains {
+        let locales = match fs::read_dir(&dirname) {
+            Ok(locales) => locales,
+            Err(_) => continue,
+        };
+        for locale in locales.filter_map(Result::ok) {
+            let path = locale.path().join("LC_MESSAGES").join(&filename);
+            if let Ok(modified) = fs::metadata(&path).and_then(|m| m.modified()) {
+                times.push((path, modified));
+            }
+        }
+    }
+    times.sort();
+    times.dedup();
+    times
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn make_catalog_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir()
+            .join(format!("gettext-rs-{}-{}", name, std::process::id()))
+            .join("locale");
+        fs::create_dir_all(dir.join("de").join("LC_MESSAGES")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn reload_rebinds_domains() {
+        let dir = make_catalog_dir("reload");
+        ::bindtextdomain("reload_test", dir.clone()).unwrap();
+
+        reload_catalogs();
+        let first = getters::domain_directory("reload_test").unwrap();
+        reload_catalogs();
+        let second = getters::domain_directory("reload_test").unwrap();
+
+        // Same directory (`Path` comparison ignores "." components), spelled differently
+        assert_eq!(first, dir);
+        assert_eq!(second, dir);
+        assert_ne!(first.as_os_str(), dir.as_os_str());
+        assert_ne!(second.as_os_str(), first.as_os_str());
+
+        // Binding the domain again changes the directory that is reloaded
+        let other = make_catalog_dir("reload-other");
+        ::bindtextdomain("reload_test", other.clone()).unwrap();
+        reload_catalogs();
+        let third = getters::domain_directory("reload_test").unwrap();
+        assert_eq!(third, other);
+
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+        fs::remove_dir_all(other.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn reloads_are_limited() {
+        let mut binding = Binding {
+            domainname: b"limit_test".to_vec(),
+            dirname: PathBuf::from("/usr/share/locale"),
+            reloads: 0,
+        };
+        let dirnames: Vec<PathBuf> = (0..MAX_RELOADS)
+            .map(|_| binding.next_dirname().unwrap())
+            .collect();
+        assert_eq!(dirnames[0].as_os_str(), "/usr/share/locale/.");
+        assert_eq!(
+            dirnames[MAX_RELOADS - 1].as_os_str().len(),
+            "/usr/share/locale".len() + 2 * MAX_RELOADS
+        );
+        assert_eq!(binding.next_dirname(), None);
+        assert_eq!(binding.next_dirname(), None);
+    }
+
+    #[test]
+    fn watcher_notices_changes() {
+        let dir = make_catalog_dir("watch");
+        ::bindtextdomain("watch_test", dir.clone()).unwrap();
+
+        let (reloaded, reloads) = mpsc::channel();
+        let watcher = watch_catalogs(Duration::from_millis(10), move || {
+            let _ = reloaded.send(());
+        });
+        // Give the watcher time to take the first snapshot
+        thread::sleep(Duration::from_millis(50));
+
+        let catalog = dir.join("de").join("LC_MESSAGES").join("watch_test.mo");
+        fs::write(&catalog, b"not really a catalog").unwrap();
+        reloads.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        drop(watcher);
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn watcher_dropped_under_lock() {
+        let (dropped, drops) = mpsc::channel();
+        thread::spawn(move || {
+            let config = GettextConfig::lock();
+            let watcher = watch_catalogs(Duration::from_millis(1), || {});
+            // Let the watcher block on the lock
+            thread::sleep(Duration::from_millis(20));
+            drop(watcher);
+            drop(config);
+            dropped.send(()).unwrap();
+        });
+        drops.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+}
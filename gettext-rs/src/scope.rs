@@ -0,0 +1,524 @@
+//! Translating into a language of one's choosing, rather than the one of the process-wide locale.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::future::Future;
+use std::mem;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::task::{Context, Poll};
+
+use super::catalog::Catalog;
+use super::text_domain::{locale_dir_names, posix_locale_name};
+use super::{getters, missing, LocaleCategory};
+
+thread_local! {
+    /// Language of the [`WithLanguage`] future that is being polled on this thread, if any.
+    static LANGUAGE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Catalogs loaded by [`translate`], keyed by path. `None` if the file doesn't exist or is invalid.
+static CATALOGS: Mutex<Option<HashMap<PathBuf, Option<Arc<Catalog>>>>> = Mutex::new(None);
+
+/// Bumped whenever the catalog that a lookup resolves to might have changed.
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// The catalog each lookup resolved to, so that lookups don't need the gettext configuration.
+static RESOLVED: RwLock<Option<Resolved>> = RwLock::new(None);
+
+struct Resolved {
+    /// Value of `GENERATION` when the catalogs were resolved.
+    generation: usize,
+    /// `None` if there is no catalog for the key.
+    catalogs: HashMap<CatalogKey, Option<Arc<Catalog>>>,
+}
+
+/// What a catalog is looked up for. A `domainname` of `None` stands for the current text domain.
+#[derive(PartialEq, Eq, Hash)]
+struct CatalogKey {
+    domainname: Option<Vec<u8>>,
+    category: LocaleCategory,
+    language: String,
+}
+
+/// Translate messages inside `future` into `language`, regardless of the current locale.
+///
+/// `language` is a language code like "de", "pt_BR" or "sr-Latn", or a list of them separated by
+/// colons, like the `LANGUAGE` environment variable. While `future` runs, [`gettext`][fn@::gettext],
+/// [`ngettext`][fn@::ngettext], and all the other lookup functions and macros of this crate look the
+/// message up in the first of these languages that has a translation for it, and return it
+/// untranslated if none does.
+///
+/// The language sticks to the future, not the thread, so it works in async runtimes that move tasks
+/// between threads. Nothing global is changed: other tasks keep using the current locale (or their
+/// own language), and lookups outside of the scope are unaffected.
+///
+/// The catalogs are read by this crate rather than libintl, from the same place libintl would read
+/// them: `<dir>/<language>/LC_MESSAGES/<domain>.mo`, where `<dir>` is the directory bound to the
+/// domain with [`bindtextdomain`][::bindtextdomain]. They are only read once; use
+/// [`reload_catalogs`] to pick up changes. Only UTF-8 catalogs are supported, and
+/// [`bind_textdomain_codeset`][::bind_textdomain_codeset] has no effect on them. Which catalog a
+/// domain and language resolve to is remembered until the text domain or its directory changes,
+/// so lookups don't wait for [`GettextConfig`] once the catalog has been found.
+///
+/// # Examples
+///
+/// ```edition2018,no_run
+/// use gettextrs::*;
+///
+/// # async fn handle_request() {
+/// bindtextdomain("my_textdomain", "/usr/local/share/locale").unwrap();
+/// textdomain("my_textdomain").unwrap();
+///
+/// let greeting = with_language("de", async { gettext("Hello, world!") }).await;
+/// # }
+/// ```
+///
+/// [`reload_catalogs`]: fn.reload_catalogs.html
+/// [`GettextConfig`]: struct.GettextConfig.html
+pub fn with_language<F: Future>(language: &str, future: F) -> WithLanguage<F> {
+    WithLanguage {
+        language: Some(language.to_owned()),
+        future,
+    }
+}
+
+/// Future returned by [`with_language`].
+///
+/// [`with_language`]: fn.with_language.html
+#[derive(Debug)]
+pub struct WithLanguage<F> {
+    /// The language while the future isn't being polled. While it is, this holds the language of
+    /// the enclosing scope instead.
+    language: Option<String>,
+    future: F,
+}
+
+impl<F: Future> Future for WithLanguage<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<F::Output> {
+        // Safety: `future` is pinned along with `self`, and is never moved out of it.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        let _scope = Scope::enter(&mut this.language);
+        future.poll(cx)
+    }
+}
+
+/// Makes a language current on this thread until dropped (even if the future panics).
+struct Scope<'a> {
+    language: &'a mut Option<String>,
+}
+
+impl<'a> Scope<'a> {
+    fn enter(language: &'a mut Option<String>) -> Scope<'a> {
+        LANGUAGE.with(|current| mem::swap(&mut *current.borrow_mut(), language));
+        Scope { language }
+    }
+}
+
+impl<'a> Drop for Scope<'a> {
+    fn drop(&mut self) {
+        LANGUAGE.with(|current| mem::swap(&mut *current.borrow_mut(), self.language));
+    }
+}
+
+/// Language of the current [`with_language`] scope, if any.
+///
+/// [`with_language`]: fn.with_language.html
+pub(crate) fn current_language() -> Option<String> {
+    LANGUAGE.with(|current| current.borrow().clone())
+}
+
+/// Look up a message in the language of the current [`with_language`] scope.
+///
+/// Returns `None` if there's no such scope, so that the caller can ask libintl instead. Otherwise,
+/// returns the translation, or `msgid` or `msgid_plural` (as appropriate for `n`) if there is none.
+/// `domainname` defaults to the current text domain.
+///
+/// [`with_language`]: fn.with_language.html
+pub(crate) fn translate(
+    domainname: Option<&CStr>,
+    category: LocaleCategory,
+    msgid: &CStr,
+    plural: Option<(&CStr, u32)>,
+) -> Option<String> {
+    let languages = current_language()?;
+    let translation = find(&languages, domainname, category, msgid, plural);
+    if translation.is_some() {
+        return translation;
+    }
+    missing::report(domainname, category, msgid, plural);
+    let untranslated = match plural {
+        Some((msgid_plural, n)) if n != 1 => msgid_plural,
+        _ => msgid,
+    };
+    // This comes from a `String`, so it is valid UTF-8
+    Some(untranslated.to_str().unwrap().to_owned())
+}
+
+/// Look the message up in the first of `languages` that has a translation for it.
+fn find(
+    languages: &str,
+    domainname: Option<&CStr>,
+    category: LocaleCategory,
+    msgid: &CStr,
+    plural: Option<(&CStr, u32)>,
+) -> Option<String> {
+    // This comes from a `String`, so it is valid UTF-8
+    let msgid = msgid.to_str().unwrap();
+    let n = plural.map(|(_, n)| n);
+
+    for language in languages.split(':').filter(|language| !language.is_empty()) {
+        let key = CatalogKey {
+            domainname: domainname.map(|domainname| domainname.to_bytes().to_owned()),
+            category,
+            language: language.to_owned(),
+        };
+        let catalog = match resolve_catalog(key) {
+            Some(catalog) => catalog,
+            None => continue,
+        };
+        let translation = match n {
+            Some(n) => catalog.ngettext(msgid, n),
+            None => catalog.gettext(msgid),
+        };
+        // Like libintl, treat empty translations as missing (except for the header)
+        if let Some(translation) = translation.filter(|t| !t.is_empty() || msgid.is_empty()) {
+            return Some(translation.to_owned());
+        }
+    }
+    None
+}
+
+/// The catalog for `key`, as resolved by an earlier lookup if possible.
+fn resolve_catalog(key: CatalogKey) -> Option<Arc<Catalog>> {
+    let generation = GENERATION.load(Ordering::SeqCst);
+    if let Some(resolved) = RESOLVED
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+    {
+        if resolved.generation == generation {
+            if let Some(catalog) = resolved.catalogs.get(&key) {
+                return catalog.clone();
+            }
+        }
+    }
+
+    let catalog = locate_catalog(&key);
+    let mut resolved = RESOLVED.write().unwrap_or_else(PoisonError::into_inner);
+    if GENERATION.load(Ordering::SeqCst) != generation {
+        // The configuration changed in the meantime, so the catalog might be the wrong one already
+        return catalog;
+    }
+    if !matches!(*resolved, Some(ref resolved) if resolved.generation == generation) {
+        *resolved = Some(Resolved {
+            generation,
+            catalogs: HashMap::new(),
+        });
+    }
+    if let Some(ref mut resolved) = *resolved {
+        resolved.catalogs.insert(key, catalog.clone());
+    }
+    catalog
+}
+
+/// Find the catalog for `key` in the directory bound to the domain, the way libintl would.
+fn locate_catalog(key: &CatalogKey) -> Option<Arc<Catalog>> {
+    let domainname = match key.domainname {
+        Some(ref domainname) => domainname.clone(),
+        None => getters::current_textdomain().ok()?,
+    };
+    let directory = getters::domain_directory(domainname.clone()).ok()?;
+    let category = category_dir(key.category)?;
+    let filename = format!("{}.mo", String::from_utf8_lossy(&domainname));
+    locale_dir_names(&posix_locale_name(&key.language))
+        .iter()
+        .find_map(|name| load_catalog(directory.join(name).join(category).join(&filename)))
+}
+
+/// Forget which catalog each lookup resolved to, e.g. because the text domain changed.
+pub(crate) fn invalidate() {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Forget all loaded catalogs, so that they are read again on the next lookup.
+pub(crate) fn clear_catalogs() {
+    invalidate();
+    *CATALOGS.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+fn load_catalog(path: PathBuf) -> Option<Arc<Catalog>> {
+    let mut catalogs = CATALOGS.lock().unwrap_or_else(|e| e.into_inner());
+    catalogs
+        .get_or_insert_with(HashMap::new)
+        .entry(path)
+        .or_insert_with_key(|path| Catalog::load(path).map(Arc::new))
+        .clone()
+}
+
+/// Name of the directory that holds the catalogs of `category`. `None` for `LcAll`, which libintl
+/// doesn't look messages up in either.
+fn category_dir(category: LocaleCategory) -> Option<&'static str> {
+    match category {
+        LocaleCategory::LcCType => Some("LC_CTYPE"),
+        LocaleCategory::LcNumeric => Some("LC_NUMERIC"),
+        LocaleCategory::LcTime => Some("LC_TIME"),
+        LocaleCategory::LcCollate => Some("LC_COLLATE"),
+        LocaleCategory::LcMonetary => Some("LC_MONETARY"),
+        LocaleCategory::LcMessages => Some("LC_MESSAGES"),
+        LocaleCategory::LcAll => None,
+        LocaleCategory::LcPaper => Some("LC_PAPER"),
+        LocaleCategory::LcName => Some("LC_NAME"),
+        LocaleCategory::LcAddress => Some("LC_ADDRESS"),
+        LocaleCategory::LcTelephone => Some("LC_TELEPHONE"),
+        LocaleCategory::LcMeasurement => Some("LC_MEASUREMENT"),
+        LocaleCategory::LcIdentification => Some("LC_IDENTIFICATION"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use catalog::tests::make_mo;
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+    use std::sync::{mpsc, Arc};
+    use std::task::{Wake, Waker};
+    use std::thread;
+    use std::time::Duration;
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    /// Future that calls `lookup` on every poll, and is pending for the first `polls - 1` of them.
+    struct Lookups<F> {
+        lookup: F,
+        results: Vec<String>,
+        polls: usize,
+    }
+
+    impl<F: FnMut() -> String + Unpin> Future for Lookups<F> {
+        type Output = Vec<String>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Vec<String>> {
+            let result = (self.lookup)();
+            self.results.push(result);
+            if self.results.len() < self.polls {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            } else {
+                Poll::Ready(mem::take(&mut self.results))
+            }
+        }
+    }
+
+    fn lookups<F: FnMut() -> String + Unpin>(polls: usize, lookup: F) -> Lookups<F> {
+        Lookups {
+            lookup,
+            results: Vec::new(),
+            polls,
+        }
+    }
+
+    fn write_catalog(
+        dir: &Path,
+        domainname: &str,
+        language: &str,
+        plural_forms: &str,
+        messages: &[(&str, &[&str])],
+    ) {
+        let header = format!(
+            "Content-Type: text/plain; charset=UTF-8\nPlural-Forms: {}\n",
+            plural_forms
+        );
+        let messages_dir = dir.join(language).join("LC_MESSAGES");
+        fs::create_dir_all(&messages_dir).unwrap();
+        let catalog = messages_dir.join(format!("{}.mo", domainname));
+        fs::write(catalog, make_mo(&header, messages)).unwrap();
+    }
+
+    /// Create German and French catalogs for `domainname`.
+    fn make_catalogs(domainname: &str) -> PathBuf {
+        let dir = env::temp_dir()
+            .join(format!("gettext-rs-{}-{}", domainname, std::process::id()))
+            .join("locale");
+        write_catalog(
+            &dir,
+            domainname,
+            "de",
+            "nplurals=2; plural=(n != 1);",
+            &[
+                ("Hello", &["Hallo"]),
+                ("Untranslated", &[""]),
+                ("One apple\0{} apples", &["Ein Apfel", "{} Äpfel"]),
+            ],
+        );
+        write_catalog(
+            &dir,
+            domainname,
+            "fr",
+            "nplurals=2; plural=(n > 1);",
+            &[("Bye", &["Au revoir"])],
+        );
+        dir
+    }
+
+    #[test]
+    fn scoped_lookups() {
+        let dir = make_catalogs("scope_test");
+        ::bindtextdomain("scope_test", dir.clone()).unwrap();
+
+        let results = block_on(with_language(
+            "de_DE.UTF-8:fr",
+            lookups(2, || ::dgettext("scope_test", "Hello")),
+        ));
+        assert_eq!(results, vec!["Hallo", "Hallo"]);
+
+        let lookup = || {
+            vec![
+                ::dgettext("scope_test", "Bye"),
+                ::dgettext("scope_test", "Untranslated"),
+                ::dgettext("scope_test", "Missing"),
+                ::dngettext("scope_test", "One apple", "{} apples", 1),
+                ::dngettext("scope_test", "One apple", "{} apples", 5),
+                ::dngettext("scope_test", "One pear", "{} pears", 5),
+                ::dcgettext("scope_test", "Hello", LocaleCategory::LcAll),
+            ]
+        };
+        let results = block_on(with_language("de:fr", async_fn(lookup)));
+        assert_eq!(
+            results,
+            vec![
+                "Au revoir",
+                "Untranslated",
+                "Missing",
+                "Ein Apfel",
+                "{} Äpfel",
+                "{} pears",
+                "Hello"
+            ]
+        );
+
+        // Outside of the scope, the (C) locale is used
+        assert_eq!(::dgettext("scope_test", "Hello"), "Hello");
+
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn nested_scopes() {
+        let dir = make_catalogs("scope_nested");
+        ::bindtextdomain("scope_nested", dir.clone()).unwrap();
+
+        let lookup = || ::dgettext("scope_nested", "Bye");
+        let results = block_on(with_language(
+            "de",
+            async_fn(move || {
+                let mut results = vec![lookup()];
+                results.extend(block_on(with_language("fr", lookups(2, lookup))));
+                results.push(lookup());
+                results
+            }),
+        ));
+        assert_eq!(results, vec!["Bye", "Au revoir", "Au revoir", "Bye"]);
+
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn language_follows_the_future() {
+        let dir = make_catalogs("scope_threads");
+        ::bindtextdomain("scope_threads", dir.clone()).unwrap();
+
+        let lookup = || ::dgettext("scope_threads", "Hello");
+        let mut future = Box::pin(with_language("de", lookups(2, lookup)));
+        let waker = Waker::from(Arc::new(NoopWaker));
+
+        // The first poll happens on this thread, and the second one on another
+        assert!(future
+            .as_mut()
+            .poll(&mut Context::from_waker(&waker))
+            .is_pending());
+        assert_eq!(lookup(), "Hello");
+        let results = thread::spawn(move || {
+            let waker = Waker::from(Arc::new(NoopWaker));
+            match future.as_mut().poll(&mut Context::from_waker(&waker)) {
+                Poll::Ready(results) => results,
+                Poll::Pending => panic!("future should be done"),
+            }
+        })
+        .join()
+        .unwrap();
+        assert_eq!(results, vec!["Hallo", "Hallo"]);
+
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn resolved_catalogs() {
+        let dir = make_catalogs("scope_resolved");
+        ::bindtextdomain("scope_resolved", dir.clone()).unwrap();
+        let lookup = || ::dgettext("scope_resolved", "Hello");
+        assert_eq!(block_on(with_language("de", async_fn(lookup))), "Hallo");
+
+        // Once resolved, lookups don't wait for the gettext configuration
+        let (locked, is_locked) = mpsc::channel();
+        let (done, is_done) = mpsc::channel::<()>();
+        let holder = thread::spawn(move || {
+            let _config = ::GettextConfig::lock();
+            locked.send(()).unwrap();
+            let _ = is_done.recv_timeout(Duration::from_secs(5));
+        });
+        is_locked.recv().unwrap();
+        let (result, results) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = result.send(block_on(with_language("de", async_fn(lookup))));
+        });
+        let result = results.recv_timeout(Duration::from_secs(1));
+        drop(done);
+        assert_eq!(result.unwrap(), "Hallo");
+        holder.join().unwrap();
+
+        // Binding the domain elsewhere resolves its catalogs anew
+        let other = make_catalogs("scope_resolved_other");
+        write_catalog(
+            &other,
+            "scope_resolved",
+            "de",
+            "nplurals=2; plural=(n != 1);",
+            &[("Hello", &["Servus"])],
+        );
+        ::bindtextdomain("scope_resolved", other.clone()).unwrap();
+        assert_eq!(block_on(with_language("de", async_fn(lookup))), "Servus");
+
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+        fs::remove_dir_all(other.parent().unwrap()).unwrap();
+    }
+
+    /// Future that returns the result of `f`, like `async { f() }`.
+    fn async_fn<T, F: FnOnce() -> T + Unpin>(f: F) -> impl Future<Output = T> {
+        let mut f = Some(f);
+        std::future::poll_fn(move |_| Poll::Ready((f.take().unwrap())()))
+    }
+}
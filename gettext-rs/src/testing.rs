@@ -0,0 +1,328 @@
+//! In-memory translations for unit tests.
+//!
+//! Tests of code that uses gettext normally need compiled MO files installed somewhere, and a
+//! locale that is installed on the machine running the tests. [`MockTranslations`] makes the
+//! lookup functions and macros of this crate return translations that the test provides instead:
+//!
+//! ```
+//! use gettextrs::testing::MockTranslations;
+//! use gettextrs::*;
+//!
+//! let _mock = MockTranslations::new("my_textdomain", "de")
+//!     .gettext("Hello, {}!", "Hallo, {}!")
+//!     .ngettext("One file", &["Eine Datei", "{} Dateien"])
+//!     .pgettext("File menu", "Open", "Öffnen")
+//!     .install();
+//!
+//! assert_eq!(dgettext!("my_textdomain", "Hello, {}!", "Welt"), "Hallo, Welt!");
+//! assert_eq!(dngettext("my_textdomain", "One file", "{} files", 3), "{} Dateien");
+//! ```
+//!
+//! Mock translations are installed for the current thread only, so that tests running in parallel
+//! don't see each other's translations. Nothing global is changed either: neither the locale nor
+//! the current text domain.
+//!
+//! [`MockTranslations`]: struct.MockTranslations.html
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::marker::PhantomData;
+
+use super::catalog::{Catalog, PluralForms};
+use super::{getters, missing, scope, LocaleCategory};
+
+/// A domain and a language.
+type Key = (Vec<u8>, String);
+
+/// Mock translations installed on a thread, and the language that lookups in each domain use by
+/// default.
+#[derive(Default)]
+struct Mocks {
+    catalogs: HashMap<Key, Catalog>,
+    languages: HashMap<Vec<u8>, String>,
+}
+
+thread_local! {
+    static MOCKS: RefCell<Mocks> = RefCell::new(Mocks::default());
+}
+
+/// Translations of a text domain into a language, to be returned by lookups without any MO files.
+///
+/// See the [module documentation](index.html) for an example.
+#[derive(Debug)]
+pub struct MockTranslations {
+    domainname: Vec<u8>,
+    language: String,
+    catalog: Catalog,
+}
+
+impl MockTranslations {
+    /// Create empty translations of `domainname` into `language` (a language code like "de" or
+    /// "pt_BR").
+    ///
+    /// Messages without a plural form use the plural forms of Germanic languages: one form for 1,
+    /// and another for all other numbers.
+    pub fn new<T: Into<Vec<u8>>>(domainname: T, language: &str) -> MockTranslations {
+        MockTranslations {
+            domainname: domainname.into(),
+            language: language.to_owned(),
+            catalog: Catalog::default(),
+        }
+    }
+
+    /// Set the plural forms, in the format of the `Plural-Forms` header of a PO file, e.g.
+    /// `nplurals=3; plural=(n==1 ? 0 : n>=2 && n<=4 ? 1 : 2);`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `plural_forms` can't be parsed.
+    pub fn plural_forms(mut self, plural_forms: &str) -> MockTranslations {
+        let plural_forms = PluralForms::parse(plural_forms).expect("invalid `plural_forms`");
+        self.catalog.set_plural_forms(plural_forms);
+        self
+    }
+
+    /// Add a translation of `msgid`, for [`gettext`][fn@::gettext] and friends.
+    pub fn gettext(mut self, msgid: &str, translation: &str) -> MockTranslations {
+        self.catalog
+            .insert(msgid.to_owned(), vec![translation.to_owned()]);
+        self
+    }
+
+    /// Add translations of `msgid` in all plural forms, for [`ngettext`][fn@::ngettext] and friends.
+    pub fn ngettext(mut self, msgid: &str, translations: &[&str]) -> MockTranslations {
+        let forms = translations.iter().map(|&form| form.to_owned()).collect();
+        self.catalog.insert(msgid.to_owned(), forms);
+        self
+    }
+
+    /// Add a translation of `msgid` in the context `msgctxt`, for [`pgettext`][fn@::pgettext].
+    pub fn pgettext(self, msgctxt: &str, msgid: &str, translation: &str) -> MockTranslations {
+        self.gettext(&format!("{}\x04{}", msgctxt, msgid), translation)
+    }
+
+    /// Add translations of `msgid` in the context `msgctxt` in all plural forms, for
+    /// [`npgettext`][fn@::npgettext].
+    pub fn npgettext(self, msgctxt: &str, msgid: &str, translations: &[&str]) -> MockTranslations {
+        self.ngettext(&format!("{}\x04{}", msgctxt, msgid), translations)
+    }
+
+    /// Make lookups on this thread use these translations, until the returned guard is dropped.
+    ///
+    /// Lookups in the domain look messages up in the language of the mock translations of that
+    /// domain that were installed last, or in the language of the enclosing [`with_language`]
+    /// scope, if any. If
+    /// there are mock translations for the domain, but not for the message or the language, the
+    /// message is returned untranslated, as if it were missing from the catalog. Domains without
+    /// mock translations are looked up as usual.
+    ///
+    /// [`with_language`]: ../fn.with_language.html
+    pub fn install(self) -> MockGuard {
+        let MockTranslations {
+            domainname,
+            language,
+            catalog,
+        } = self;
+        let key = (domainname.clone(), language.clone());
+        MOCKS.with(|mocks| {
+            let mut mocks = mocks.borrow_mut();
+            let language = mocks.languages.insert(domainname, language);
+            let catalog = mocks.catalogs.insert(key.clone(), catalog);
+            MockGuard {
+                key,
+                catalog,
+                language,
+                _not_send: PhantomData,
+            }
+        })
+    }
+}
+
+/// Keeps [`MockTranslations`] installed. When dropped, restores whatever was installed before.
+///
+/// [`MockTranslations`]: struct.MockTranslations.html
+#[derive(Debug)]
+#[must_use = "the mock translations are removed when the guard is dropped"]
+pub struct MockGuard {
+    key: Key,
+    /// Mock translations of the same domain and language that were replaced.
+    catalog: Option<Catalog>,
+    /// The domain's language before these mock translations were installed.
+    language: Option<String>,
+    // The translations belong to the thread that installed them
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for MockGuard {
+    fn drop(&mut self) {
+        // The thread is exiting if this fails, and the mocks go away with it anyway
+        let _ = MOCKS.try_with(|mocks| {
+            let mut mocks = mocks.borrow_mut();
+            match self.catalog.take() {
+                Some(catalog) => mocks.catalogs.insert(self.key.clone(), catalog),
+                None => mocks.catalogs.remove(&self.key),
+            };
+            let domainname = self.key.0.clone();
+            match self.language.take() {
+                Some(language) => mocks.languages.insert(domainname, language),
+                None => mocks.languages.remove(&domainname),
+            };
+        });
+    }
+}
+
+/// Whether any mock translations are installed on this thread.
+pub(crate) fn active() -> bool {
+    MOCKS.with(|mocks| !mocks.borrow().catalogs.is_empty())
+}
+
+/// Look up a message in the mock translations installed on this thread.
+///
+/// Returns `None` if there are none for the domain, so that the caller can look the message up as
+/// usual. Otherwise, returns the translation, or `msgid` or `msgid_plural` (as appropriate for `n`)
+/// if there is none. `domainname` defaults to the current text domain.
+pub(crate) fn translate(
+    domainname: Option<&CStr>,
+    category: LocaleCategory,
+    msgid: &CStr,
+    plural: Option<(&CStr, u32)>,
+) -> Option<String> {
+    let translation = MOCKS.with(|mocks| {
+        let mocks = mocks.borrow();
+        if mocks.catalogs.is_empty() {
+            return None;
+        }
+        let domain = match domainname {
+            Some(domainname) => domainname.to_bytes().to_owned(),
+            None => getters::current_textdomain().ok()?,
+        };
+        if !mocks
+            .catalogs
+            .keys()
+            .any(|(domainname, _)| *domainname == domain)
+        {
+            return None;
+        }
+
+        // This comes from a `String`, so it is valid UTF-8
+        let msgid = msgid.to_str().unwrap();
+        let languages =
+            scope::current_language().or_else(|| mocks.languages.get(&domain).cloned())?;
+        let translation = languages.split(':').find_map(|language| {
+            let catalog = mocks.catalogs.get(&(domain.clone(), language.to_owned()))?;
+            match plural {
+                Some((_, n)) => catalog.ngettext(msgid, n),
+                None => catalog.gettext(msgid),
+            }
+        });
+        Some(translation.map(str::to_owned))
+    })?;
+    if translation.is_some() {
+        return translation;
+    }
+
+    // Outside of `MOCKS.with()`, in case the hook installs mocks of its own
+    missing::report(domainname, category, msgid, plural);
+    let untranslated = match plural {
+        Some((msgid_plural, n)) if n != 1 => msgid_plural,
+        _ => msgid,
+    };
+    Some(untranslated.to_str().unwrap().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn lookups() {
+        let _mock = MockTranslations::new("testing_lookups", "de")
+            .gettext("Hello", "Hallo")
+            .ngettext("One file", &["Eine Datei", "{} Dateien"])
+            .pgettext("File menu", "Open", "Öffnen")
+            .npgettext(
+                "Inbox",
+                "One message",
+                &["Eine Nachricht", "{} Nachrichten"],
+            )
+            .install();
+
+        assert_eq!(::dgettext("testing_lookups", "Hello"), "Hallo");
+        assert_eq!(::dgettext("testing_lookups", "Goodbye"), "Goodbye");
+        assert_eq!(
+            ::dngettext("testing_lookups", "One file", "{} files", 1),
+            "Eine Datei"
+        );
+        assert_eq!(
+            ::dngettext!("testing_lookups", "One file", "{} files", 7, 7),
+            "7 Dateien"
+        );
+        assert_eq!(
+            ::dngettext("testing_lookups", "One dir", "{} dirs", 7),
+            "{} dirs"
+        );
+        // Other domains aren't affected
+        assert_eq!(::dgettext("testing_other", "Hello"), "Hello");
+
+        let _config = ::GettextConfig::lock();
+        ::textdomain("testing_lookups").unwrap();
+        assert_eq!(::gettext("Hello"), "Hallo");
+        assert_eq!(::pgettext("File menu", "Open"), "Öffnen");
+        assert_eq!(::pgettext("Other menu", "Open"), "Open");
+        assert_eq!(
+            ::npgettext("Inbox", "One message", "{} messages", 2),
+            "{} Nachrichten"
+        );
+    }
+
+    #[test]
+    fn plural_forms() {
+        let _mock = MockTranslations::new("testing_plural_forms", "cs")
+            .plural_forms("nplurals=3; plural=(n==1) ? 0 : (n>=2 && n<=4) ? 1 : 2;")
+            .ngettext("One file", &["Jeden soubor", "{} soubory", "{} souborů"])
+            .install();
+
+        let lookup = |n| ::dngettext("testing_plural_forms", "One file", "{} files", n);
+        assert_eq!(lookup(1), "Jeden soubor");
+        assert_eq!(lookup(3), "{} soubory");
+        assert_eq!(lookup(5), "{} souborů");
+    }
+
+    #[test]
+    fn guards() {
+        let german = MockTranslations::new("testing_guards", "de")
+            .gettext("Hello", "Hallo")
+            .install();
+        let french = MockTranslations::new("testing_guards", "fr")
+            .gettext("Hello", "Bonjour")
+            .install();
+        assert_eq!(::dgettext("testing_guards", "Hello"), "Bonjour");
+
+        // Other threads don't see the mocks
+        let other = thread::spawn(|| ::dgettext("testing_guards", "Hello"));
+        assert_eq!(other.join().unwrap(), "Hello");
+
+        drop(french);
+        assert_eq!(::dgettext("testing_guards", "Hello"), "Hallo");
+        drop(german);
+        assert_eq!(::dgettext("testing_guards", "Hello"), "Hello");
+    }
+
+    #[test]
+    fn language_per_domain() {
+        let _app = MockTranslations::new("testing_app", "de")
+            .gettext("Hello", "Hallo")
+            .install();
+        let library = MockTranslations::new("testing_library", "fr")
+            .gettext("Hello", "Bonjour")
+            .install();
+        assert_eq!(::dgettext("testing_app", "Hello"), "Hallo");
+        assert_eq!(::dgettext("testing_library", "Hello"), "Bonjour");
+
+        drop(library);
+        assert_eq!(::dgettext("testing_app", "Hello"), "Hallo");
+        assert_eq!(::dgettext("testing_library", "Hello"), "Hello");
+    }
+}
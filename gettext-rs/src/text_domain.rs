@@ -5,10 +5,12 @@ use locale_config::{LanguageRange, Locale};
 use std::env;
 use std::error;
 use std::fmt;
-use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
-use super::{bind_textdomain_codeset, bindtextdomain, setlocale, textdomain, LocaleCategory};
+use super::{
+    bind_textdomain_codeset, bindtextdomain, setlocale, textdomain, GettextConfig, LocaleCategory,
+};
 
 /// Errors that might come up after running the builder.
 #[derive(Debug)]
@@ -16,7 +18,23 @@ pub enum TextDomainError {
     /// The locale is malformed.
     InvalidLocale(String),
     /// The translation for the requested language could not be found or the search path is empty.
-    TranslationNotFound(String),
+    TranslationNotFound {
+        /// The language part of each requested language, separated by colons, e.g. "de:fr".
+        language: String,
+        /// The text domain that has no translation.
+        domainname: String,
+        /// All the search paths, in order.
+        searched_paths: Vec<PathBuf>,
+        /// The locale directories that were checked for a translation, in order.
+        candidates: Vec<PathBuf>,
+    },
+    /// The call to `setlocale()` failed, e.g. because the locale is not installed.
+    SetLocaleFailed {
+        /// The requested locale. An empty string stands for the user's default locale.
+        locale: String,
+        /// The category passed to `setlocale()`.
+        category: LocaleCategory,
+    },
     /// The call to `textdomain()` failed.
     TextDomainCallFailed(std::io::Error),
     /// The call to `bindtextdomain()` failed.
@@ -31,8 +49,34 @@ impl fmt::Display for TextDomainError {
 
         match self {
             InvalidLocale(locale) => write!(f, r#"Locale "{}" is invalid."#, locale),
-            TranslationNotFound(language) => {
-                write!(f, "Translations not found for language {}.", language)
+            TranslationNotFound {
+                language,
+                domainname,
+                searched_paths,
+                candidates,
+            } => {
+                write!(
+                    f,
+                    r#"Translations not found for language {} in text domain "{}"."#,
+                    language, domainname
+                )?;
+                write!(f, " Searched paths: {:?}.", searched_paths)?;
+                write!(f, " Checked locale directories: {:?}.", candidates)
+            }
+            SetLocaleFailed { locale, category } => {
+                if locale.is_empty() {
+                    write!(
+                        f,
+                        "The call to setlocale() failed for the default locale and category {:?}.",
+                        category
+                    )
+                } else {
+                    write!(
+                        f,
+                        r#"The call to setlocale() failed for locale "{}" and category {:?}."#,
+                        locale, category
+                    )
+                }
             }
             TextDomainCallFailed(inner) => write!(f, "The call to textdomain() failed: {}", inner),
             BindTextDomainCallFailed(inner) => {
@@ -51,7 +95,8 @@ impl error::Error for TextDomainError {
 
         match self {
             InvalidLocale(_) => None,
-            TranslationNotFound(_) => None,
+            TranslationNotFound { .. } => None,
+            SetLocaleFailed { .. } => None,
             TextDomainCallFailed(inner) => Some(inner),
             BindTextDomainCallFailed(inner) => Some(inner),
             BindTextDomainCodesetCallFailed(inner) => Some(inner),
@@ -59,6 +104,66 @@ impl error::Error for TextDomainError {
     }
 }
 
+/// The result of a successful [`TextDomain::init`].
+///
+/// [`TextDomain::init`]: struct.TextDomain.html#method.init
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextDomainInit {
+    /// The opaque string that describes the locale set, i.e. the result of [`setlocale`].
+    ///
+    /// [`setlocale`]: fn.setlocale.html
+    pub locale: Vec<u8>,
+    /// What was found and applied.
+    pub resolution: TextDomainResolution,
+}
+
+/// A report of what [`TextDomain::init`] does, as returned by [`TextDomain::resolve`].
+///
+/// [`TextDomain::init`]: struct.TextDomain.html#method.init
+/// [`TextDomain::resolve`]: struct.TextDomain.html#method.resolve
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextDomainResolution {
+    /// The locales passed to [`setlocale`], in order, until one of them is accepted. An empty
+    /// string stands for the user's default locale.
+    ///
+    /// [`setlocale`]: fn.setlocale.html
+    pub locales: Vec<String>,
+    /// The language priority list that was searched.
+    pub languages: Vec<String>,
+    /// All the search paths, in order.
+    pub searched_paths: Vec<PathBuf>,
+    /// The primary text domain, which is passed to [`textdomain`].
+    ///
+    /// [`textdomain`]: fn.textdomain.html
+    pub domain: DomainResolution,
+    /// The extra text domains added with [`TextDomain::extra_domain`], in order.
+    ///
+    /// [`TextDomain::extra_domain`]: struct.TextDomain.html#method.extra_domain
+    pub extra_domains: Vec<DomainResolution>,
+}
+
+/// The part of a [`TextDomainResolution`] that is specific to one text domain.
+///
+/// [`TextDomainResolution`]: struct.TextDomainResolution.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DomainResolution {
+    /// The text domain.
+    pub domainname: String,
+    /// The locale directories that were checked for a translation, in order, e.g.
+    /// "/usr/share/locale/de_CH".
+    pub candidates: Vec<PathBuf>,
+    /// The entry of the language priority list for which a translation was found, e.g. "de" if
+    /// `LANGUAGE` is "de_CH:de:en" and there is no translation for "de_CH". `None` for the "C"
+    /// and "POSIX" locales.
+    pub language: Option<String>,
+    /// The directory passed to [`bindtextdomain`].
+    ///
+    /// [`bindtextdomain`]: fn.bindtextdomain.html
+    pub directory: Option<PathBuf>,
+    /// The translation file that was found.
+    pub catalog: Option<PathBuf>,
+}
+
 /// A builder to configure gettext.
 ///
 /// It searches translations in the system data paths and optionally in the user-specified paths,
@@ -72,25 +177,38 @@ impl error::Error for TextDomainError {
 /// UTF-8](./index.html#utf-8-is-required).
 /// - Current user's locale is selected by default. You can override this behaviour by calling
 /// [`locale`].
+/// - The languages to search translations for are taken from the `LANGUAGE` environment variable
+/// or the locale (see below). Use [`languages`] to provide your own list.
 /// - [`LocaleCategory::LcMessages`] is used when calling [`setlocale`]. Use [`locale_category`]
 /// to override.
-/// - System data paths are searched by default (see below for details). Use
-/// [`skip_system_data_paths`] to limit the search to user-provided paths.
+/// - User and system data paths are searched by default (see below for details). Use
+/// [`skip_system_data_paths`] to limit the search to user-provided paths, or
+/// [`skip_user_data_path`] to only skip the user's data directory.
 ///
 /// # Text domain path binding
 ///
 /// A translation file for the text domain is searched in the following paths (in order):
 ///
-/// 1. Paths added using the [`prepend`] function.
+/// 1. Paths added using the [`prepend`] and [`prepend_exe_relative`] functions.
+/// 1. The path from the `XDG_DATA_HOME` environment variable, except if either
+/// [`skip_user_data_path`] or [`skip_system_data_paths`] was invoked. If `XDG_DATA_HOME` is not
+/// set, or is not an absolute path, the default of "$HOME/.local/share" is used.
 /// 1. Paths from the `XDG_DATA_DIRS` environment variable, except if the function
 /// [`skip_system_data_paths`] was invoked. If `XDG_DATA_DIRS` is not set, or is empty, the default
 /// of "/usr/local/share/:/usr/share/" is used.
-/// 1. Paths added using the [`push`] function.
+/// 1. Paths added using the [`push`] and [`push_exe_relative`] functions.
+///
+/// For each `path` in the search paths, the translation file is looked up as
+/// `path/locale/name/LC_MESSAGES/domainname.mo`, where `name` goes from the most specific to the
+/// least specific form of the selected locale, just like libintl does it. For example, for
+/// "de_CH.UTF-8" these are `de_CH.UTF-8`, `de_CH.utf8`, `de_CH`, `de.UTF-8`, `de.utf8`, and `de`.
+/// The first `path` containing such a file is used for the call to [`bindtextdomain`].
 ///
-/// For each `path` in the search paths, the following subdirectories are scanned:
-/// `path/locale/lang*/LC_MESSAGES` (where `lang` is the language part of the selected locale).
-/// The first `path` containing a file matching `domainname.mo` is used for the call to
-/// [`bindtextdomain`].
+/// If neither [`languages`] nor [`locale`] were used, the languages are taken from the `LANGUAGE`
+/// environment variable, just like libintl does: it's a colon-separated priority list such as
+/// "de_CH:de:en". The search paths are scanned for each language in turn, and the first language
+/// with a translation wins. If `LANGUAGE` is not set or empty, or the current locale is "C" or
+/// "POSIX", the language of the current locale is used.
 ///
 /// # Examples
 ///
@@ -130,8 +248,8 @@ impl error::Error for TextDomainError {
 ///     .locale("fr_FR")
 ///     .init()
 /// {
-///     Ok(locale) => {
-///         format!("translation found, `setlocale` returned {:?}", locale)
+///     Ok(init) => {
+///         format!("translation found, `setlocale` returned {:?}", init.locale)
 ///     }
 ///     Err(error) => {
 ///         format!("an error occurred: {}", error)
@@ -146,19 +264,27 @@ impl error::Error for TextDomainError {
 /// [`textdomain`]: fn.textdomain.html
 /// [`LocaleCategory::LcMessages`]: enum.LocaleCategory.html#variant.LcMessages
 /// [`locale`]: struct.TextDomain.html#method.locale
+/// [`languages`]: struct.TextDomain.html#method.languages
 /// [`locale_category`]: struct.TextDomain.html#method.locale_category
 /// [`codeset`]: struct.TextDomain.html#method.codeset
 /// [`skip_system_data_paths`]: struct.TextDomain.html#method.skip_system_data_paths
+/// [`skip_user_data_path`]: struct.TextDomain.html#method.skip_user_data_path
 /// [`prepend`]: struct.TextDomain.html#method.prepend
 /// [`push`]: struct.TextDomain.html#method.push
+/// [`prepend_exe_relative`]: struct.TextDomain.html#method.prepend_exe_relative
+/// [`push_exe_relative`]: struct.TextDomain.html#method.push_exe_relative
 pub struct TextDomain {
     domainname: String,
+    extra_domains: Vec<String>,
     locale: Option<String>,
+    languages: Vec<String>,
     locale_category: LocaleCategory,
     codeset: String,
-    pre_paths: Vec<PathBuf>,
-    post_paths: Vec<PathBuf>,
+    pre_paths: Vec<SearchPath>,
+    post_paths: Vec<SearchPath>,
     skip_system_data_paths: bool,
+    skip_user_data_path: bool,
+    ignore_missing_translations: bool,
 }
 
 impl TextDomain {
@@ -174,17 +300,51 @@ impl TextDomain {
     pub fn new<S: Into<String>>(domainname: S) -> TextDomain {
         TextDomain {
             domainname: domainname.into(),
+            extra_domains: vec![],
             locale: None,
+            languages: vec![],
             locale_category: LocaleCategory::LcMessages,
             codeset: "UTF-8".to_string(),
             pre_paths: vec![],
             post_paths: vec![],
             skip_system_data_paths: false,
+            skip_user_data_path: false,
+            ignore_missing_translations: false,
         }
     }
 
+    /// Add another text domain to search translations for, e.g. the domain of a library crate.
+    ///
+    /// Extra domains are searched in the same paths and for the same languages as the main one,
+    /// and [`init`] binds them with [`bindtextdomain`] and [`bind_textdomain_codeset`] as well.
+    /// Only the main domain is passed to [`textdomain`], so use [`dgettext`] and friends to look
+    /// up messages from extra domains.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use gettextrs::TextDomain;
+    ///
+    /// let text_domain = TextDomain::new("my_textdomain")
+    ///                              .extra_domain("my_library");
+    /// ```
+    ///
+    /// [`init`]: struct.TextDomain.html#method.init
+    /// [`bindtextdomain`]: fn.bindtextdomain.html
+    /// [`bind_textdomain_codeset`]: fn.bind_textdomain_codeset.html
+    /// [`textdomain`]: fn.textdomain.html
+    /// [`dgettext`]: fn.dgettext.html
+    pub fn extra_domain<S: Into<String>>(mut self, domainname: S) -> Self {
+        self.extra_domains.push(domainname.into());
+        self
+    }
+
     /// Override the `locale` for the `TextDomain`. Default is to use current locale.
     ///
+    /// The "C" and "POSIX" locales stand for the untranslated source language: no translations
+    /// are searched for, but [`init`] still sets the locale, the codeset, and the text domain. This
+    /// lets a running application switch back to the source language.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -193,11 +353,56 @@ impl TextDomain {
     /// let text_domain = TextDomain::new("my_textdomain")
     ///                              .locale("fr_FR.UTF-8");
     /// ```
+    ///
+    /// [`init`]: struct.TextDomain.html#method.init
     pub fn locale(mut self, locale: &str) -> Self {
         self.locale = Some(locale.to_owned());
         self
     }
 
+    /// Set the languages to search translations for, in order of preference. Default is to use
+    /// the `LANGUAGE` environment variable or the language of the current locale.
+    ///
+    /// libintl translates into the language of the locale, so [`init`] switches to a locale of the
+    /// selected language. The first language that has an available translation and an installed
+    /// locale is selected; languages without one are skipped. [`init`] tries the locale requested
+    /// with [`locale`] (or the current locale) if it is for the same language, and then the
+    /// installed locales of the language as listed by `locale -a`, UTF-8 ones first (e.g.
+    /// "de_DE.utf8" and "de_AT.utf8" for "de"). If the installed locales can't be listed, the
+    /// usual names are guessed instead (e.g. "de.UTF-8", "de.utf8" and "de"). If no language has
+    /// both, [`init`] fails with [`TextDomainError::SetLocaleFailed`] rather than translate into
+    /// another language. The environment is left alone; note that libintl prefers the user's
+    /// `LANGUAGE` environment variable, if it is set, over the locale.
+    ///
+    /// Extra domains (see [`extra_domain`]) are searched in the language selected for the primary
+    /// domain only, since libintl looks them up in the same locale.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use gettextrs::TextDomain;
+    ///
+    /// let text_domain = TextDomain::new("my_textdomain")
+    ///                              .languages(["de_CH", "de", "fr"]);
+    /// ```
+    ///
+    /// [`init`]: struct.TextDomain.html#method.init
+    /// [`locale`]: struct.TextDomain.html#method.locale
+    /// [`extra_domain`]: struct.TextDomain.html#method.extra_domain
+    /// [`TextDomainError::SetLocaleFailed`]: enum.TextDomainError.html#variant.SetLocaleFailed
+    pub fn languages<I, S>(mut self, languages: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.languages = languages
+            .into_iter()
+            .map(|language| posix_locale_name(language.as_ref()))
+            .filter(|language| !language.is_empty())
+            .collect();
+        self
+    }
+
     /// Override the `locale_category`. Default is [`LocaleCategory::LcMessages`].
     ///
     /// # Examples
@@ -246,7 +451,7 @@ impl TextDomain {
     ///                              .prepend("~/.local/share");
     /// ```
     pub fn prepend<P: Into<PathBuf>>(mut self, path: P) -> Self {
-        self.pre_paths.push(path.into());
+        self.pre_paths.push(SearchPath::Fixed(path.into()));
         self
     }
 
@@ -261,11 +466,54 @@ impl TextDomain {
     ///                              .push("test");
     /// ```
     pub fn push<P: Into<PathBuf>>(mut self, path: P) -> Self {
-        self.post_paths.push(path.into());
+        self.post_paths.push(SearchPath::Fixed(path.into()));
+        self
+    }
+
+    /// Prepend the given `path`, relative to the directory of the running executable, to the search
+    /// paths.
+    ///
+    /// This is meant for relocatable installs, where translations live next to the binary no
+    /// matter where it was unpacked. Symlinks to the executable are resolved first, so the path is
+    /// relative to the actual binary. If the executable's location can't be determined, the path
+    /// is skipped.
+    ///
+    /// # Examples
+    ///
+    /// For a binary in `<prefix>/bin` and translations in `<prefix>/share/locale`:
+    ///
+    /// ```no_run
+    /// use gettextrs::TextDomain;
+    ///
+    /// let text_domain = TextDomain::new("my_textdomain")
+    ///                              .prepend_exe_relative("../share");
+    /// ```
+    pub fn prepend_exe_relative<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.pre_paths.push(SearchPath::ExeRelative(path.into()));
+        self
+    }
+
+    /// Push the given `path`, relative to the directory of the running executable, to the end of
+    /// the search paths.
+    ///
+    /// See [`prepend_exe_relative`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use gettextrs::TextDomain;
+    ///
+    /// let text_domain = TextDomain::new("my_textdomain")
+    ///                              .push_exe_relative("../share");
+    /// ```
+    ///
+    /// [`prepend_exe_relative`]: struct.TextDomain.html#method.prepend_exe_relative
+    pub fn push_exe_relative<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.post_paths.push(SearchPath::ExeRelative(path.into()));
         self
     }
 
-    /// Don't search for translations in the system data paths.
+    /// Don't search for translations in the system data paths, nor in the user's data directory.
     ///
     /// # Examples
     ///
@@ -281,14 +529,62 @@ impl TextDomain {
         self
     }
 
+    /// Don't search for translations in the user's data directory (`XDG_DATA_HOME`), but still
+    /// search the system data paths.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use gettextrs::TextDomain;
+    ///
+    /// let text_domain = TextDomain::new("my_textdomain")
+    ///                              .skip_user_data_path();
+    /// ```
+    pub fn skip_user_data_path(mut self) -> Self {
+        self.skip_user_data_path = true;
+        self
+    }
+
+    /// Don't fail if no translation is found; set up the locale, the codeset, and the text domain
+    /// anyway.
+    ///
+    /// This is handy when the source language is one of the supported ones: e.g. English users
+    /// don't need a catalog, but the locale still has to be initialized. Domains without a
+    /// translation are not passed to [`bindtextdomain`], and their [`DomainResolution::catalog`]
+    /// is `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use gettextrs::TextDomain;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let init = TextDomain::new("my_textdomain")
+    ///     .ignore_missing_translations()
+    ///     .init()?;
+    /// if init.resolution.domain.catalog.is_none() {
+    ///     println!("No translation found, messages will be shown untranslated");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`bindtextdomain`]: fn.bindtextdomain.html
+    /// [`DomainResolution::catalog`]: struct.DomainResolution.html#structfield.catalog
+    pub fn ignore_missing_translations(mut self) -> Self {
+        self.ignore_missing_translations = true;
+        self
+    }
+
     /// Search for translations in the search paths, initialize the locale, set up the text domain
     /// and ask gettext to convert messages to UTF-8.
     ///
-    /// Returns an `Option` with the opaque string that describes the locale set (i.e. the result
-    /// of [`setlocale`]) if:
+    /// This applies what [`resolve`] finds. Returns a [`TextDomainInit`] with the opaque string
+    /// that describes the locale set (i.e. the result of [`setlocale`]) and the resolution, if:
     ///
-    /// - a translation of the text domain in the requested language was found; and
-    /// - the locale is valid.
+    /// - a translation of the text domain in one of the requested languages was found (or
+    ///   [`ignore_missing_translations`] was used, or the "C" or "POSIX" locale was requested); and
+    /// - the locale is valid and [`setlocale`] accepts it.
     ///
     /// # Examples
     ///
@@ -296,25 +592,123 @@ impl TextDomain {
     /// use gettextrs::TextDomain;
     ///
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// TextDomain::new("my_textdomain").init()?;
+    /// let init = TextDomain::new("my_textdomain").init()?;
+    /// println!("Using translations for {:?}", init.resolution.domain.language);
     /// # Ok(())
     /// # }
     /// ```
     ///
+    /// [`resolve`]: struct.TextDomain.html#method.resolve
+    /// [`ignore_missing_translations`]: struct.TextDomain.html#method.ignore_missing_translations
+    /// [`TextDomainInit`]: struct.TextDomainInit.html
     /// [`TextDomainError`]: enum.TextDomainError.html
     /// [`setlocale`]: fn.setlocale.html
-    pub fn init(mut self) -> Result<Option<Vec<u8>>, TextDomainError> {
-        let (req_locale, norm_locale) = match self.locale.take() {
-            Some(req_locale) => {
-                if req_locale == "C" || req_locale == "POSIX" {
-                    return Ok(Some(req_locale.as_bytes().to_owned()));
+    pub fn init(self) -> Result<TextDomainInit, TextDomainError> {
+        let resolution = self.resolve()?;
+
+        // Other threads shouldn't see a half-applied configuration
+        let _config = GettextConfig::lock();
+
+        let result = resolution
+            .locales
+            .iter()
+            .find_map(|locale| setlocale(self.locale_category, locale.as_str()));
+        match result {
+            Some(ref locale) => debug!(
+                "switched {:?} to locale {:?} for domain {:?}",
+                self.locale_category,
+                String::from_utf8_lossy(locale),
+                self.domainname
+            ),
+            None => debug!(
+                "couldn't switch {:?} to any of the locales {:?} for domain {:?}",
+                self.locale_category, resolution.locales, self.domainname
+            ),
+        }
+        let result = result.ok_or_else(|| TextDomainError::SetLocaleFailed {
+            locale: resolution.locales[0].clone(),
+            category: self.locale_category,
+        })?;
+        for domain in Some(&resolution.domain)
+            .into_iter()
+            .chain(&resolution.extra_domains)
+        {
+            if let Some(ref directory) = domain.directory {
+                bindtextdomain(domain.domainname.clone(), directory.clone())
+                    .map_err(TextDomainError::BindTextDomainCallFailed)?;
+                debug!("bound domain {:?} to {:?}", domain.domainname, directory);
+            }
+            bind_textdomain_codeset(domain.domainname.clone(), self.codeset.clone())
+                .map_err(TextDomainError::BindTextDomainCodesetCallFailed)?;
+        }
+        textdomain(self.domainname.clone()).map_err(TextDomainError::TextDomainCallFailed)?;
+        debug!("switched to domain {:?}", self.domainname);
+
+        Ok(TextDomainInit {
+            locale: result,
+            resolution,
+        })
+    }
+
+    /// Search for translations in the search paths and report what [`init`] would do, without
+    /// changing the locale or any gettext settings.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use gettextrs::TextDomain;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let resolution = TextDomain::new("my_textdomain").resolve()?;
+    /// println!("Searched paths: {:?}", resolution.searched_paths);
+    /// println!("Would use catalog: {:?}", resolution.domain.catalog);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`init`]: struct.TextDomain.html#method.init
+    pub fn resolve(&self) -> Result<TextDomainResolution, TextDomainError> {
+        let installed = if self.languages.is_empty() {
+            None
+        } else {
+            installed_locales()
+        };
+        self.resolve_with(installed.as_deref())
+    }
+
+    /// Does the work of [`resolve`], with the installed locales passed in, or `None` if they
+    /// couldn't be listed.
+    ///
+    /// [`resolve`]: struct.TextDomain.html#method.resolve
+    fn resolve_with(
+        &self,
+        installed: Option<&[String]>,
+    ) -> Result<TextDomainResolution, TextDomainError> {
+        let (req_locale, languages) = match self.locale {
+            Some(ref req_locale) => {
+                if self.is_c_locale() {
+                    debug!("locale {:?} needs no translations", req_locale);
+                    return Ok(TextDomainResolution {
+                        locales: vec![req_locale.clone()],
+                        languages: vec![],
+                        searched_paths: vec![],
+                        domain: DomainResolution::untranslated(&self.domainname),
+                        extra_domains: self
+                            .extra_domains
+                            .iter()
+                            .map(|domainname| DomainResolution::untranslated(domainname))
+                            .collect(),
+                    });
                 }
-                match LanguageRange::new(&req_locale) {
-                    Ok(lang_range) => (req_locale.clone(), lang_range.into()),
+                match LanguageRange::new(req_locale) {
+                    Ok(lang_range) => (
+                        req_locale.clone(),
+                        vec![posix_locale_name(lang_range.as_ref())],
+                    ),
                     Err(_) => {
                         // try again as unix language tag
-                        match LanguageRange::from_unix(&req_locale) {
-                            Ok(lang_range) => (req_locale.clone(), lang_range.into()),
+                        match LanguageRange::from_unix(req_locale) {
+                            Ok(_) => (req_locale.clone(), vec![req_locale.clone()]),
                             Err(_) => {
                                 return Err(TextDomainError::InvalidLocale(req_locale.clone()));
                             }
@@ -324,66 +718,482 @@ impl TextDomain {
             }
             None => {
                 // `setlocale` accepts an empty string for current locale
-                ("".to_owned(), Locale::current())
+                ("".to_owned(), get_language_priority_list())
             }
         };
 
-        let lang = norm_locale.as_ref().splitn(2, "-").collect::<Vec<&str>>()[0].to_owned();
-
-        let domainname = self.domainname;
-        let locale_category = self.locale_category;
-        let codeset = self.codeset;
-
-        let mo_rel_path = PathBuf::from("LC_MESSAGES").join(&format!("{}.mo", &domainname));
+        let languages_requested = !self.languages.is_empty();
+        let languages = if languages_requested {
+            self.languages.clone()
+        } else {
+            languages
+        };
 
-        // Get paths from system data dirs if requested so
+        // Get the user data dir and paths from system data dirs if requested so
+        let user_data_path = if !self.skip_system_data_paths && !self.skip_user_data_path {
+            get_user_data_path()
+        } else {
+            None
+        };
         let sys_data_paths_str = if !self.skip_system_data_paths {
             get_system_data_paths()
         } else {
             "".to_owned()
         };
-        let sys_data_dirs_iter = env::split_paths(&sys_data_paths_str);
+        let sys_data_dirs_iter =
+            env::split_paths(&sys_data_paths_str).filter(|path| !path.as_os_str().is_empty());
 
-        // Chain search paths and search for the translation mo file
-        self.pre_paths
-            .into_iter()
+        // Chain search paths; they are scanned once for each language, in order of priority
+        let searched_paths: Vec<PathBuf> = self
+            .pre_paths
+            .iter()
+            .filter_map(SearchPath::resolve)
+            .chain(user_data_path)
             .chain(sys_data_dirs_iter)
-            .chain(self.post_paths.into_iter())
-            .find(|path| {
-                let locale_path = path.join("locale");
-                if !locale_path.is_dir() {
-                    return false;
+            .chain(self.post_paths.iter().filter_map(SearchPath::resolve))
+            .collect();
+        debug!(
+            "searching translations of {:?} for languages {:?} in {:?}",
+            self.domainname, languages, searched_paths
+        );
+
+        // libintl translates into the language of the locale, so a language can only be selected
+        // if there is a locale for it to switch to
+        let req_language = if !languages_requested {
+            String::new()
+        } else if req_locale.is_empty() {
+            current_locale_language()
+        } else {
+            posix_locale_name(&req_locale)
+        };
+        let locales_for =
+            |language: &str| locales_for_language(language, &req_locale, &req_language, installed);
+        let mut without_locale = None;
+        let domain =
+            DomainResolution::search(&self.domainname, &languages, &searched_paths, |language| {
+                if !languages_requested || !locales_for(language).is_empty() {
+                    return true;
                 }
+                debug!(
+                    "skipping language {:?}: no locale is installed for it",
+                    language
+                );
+                without_locale.get_or_insert_with(|| language.to_owned());
+                false
+            });
+        // The locale is switched to the language selected for the primary domain, and libintl
+        // looks up all domains in that language
+        let selected_language = domain.language.clone().filter(|_| languages_requested);
+        let domain_languages = match selected_language {
+            Some(ref language) => vec![language.clone()],
+            None => languages.clone(),
+        };
+        let extra_domains: Vec<DomainResolution> = self
+            .extra_domains
+            .iter()
+            .map(|domainname| {
+                DomainResolution::search(domainname, &domain_languages, &searched_paths, |_| true)
+            })
+            .collect();
+        if !self.ignore_missing_translations {
+            if let (None, Some(language)) = (&domain.catalog, without_locale) {
+                return Err(TextDomainError::SetLocaleFailed {
+                    locale: language,
+                    category: self.locale_category,
+                });
+            }
+            let missing = Some(&domain)
+                .into_iter()
+                .chain(&extra_domains)
+                .find(|domain| domain.catalog.is_none());
+            if let Some(missing) = missing {
+                let langs: Vec<&str> = languages.iter().map(|l| language_code(l)).collect();
+                return Err(TextDomainError::TranslationNotFound {
+                    language: langs.join(":"),
+                    domainname: missing.domainname.clone(),
+                    searched_paths,
+                    candidates: missing.candidates.clone(),
+                });
+            }
+        }
 
-                // path contains a `locale` directory
-                // search for sub directories matching `lang*`
-                // and see if we can find a translation file for the `textdomain`
-                // under `path/locale/lang*/LC_MESSAGES/`
-                if let Ok(entry_iter) = fs::read_dir(&locale_path) {
-                    return entry_iter
-                        .filter_map(|entry_res| entry_res.ok())
-                        .filter(|entry| matches!(entry.file_type().map(|ft| ft.is_dir()), Ok(true)))
-                        .any(|entry| {
-                            if let Some(entry_name) = entry.file_name().to_str() {
-                                return entry_name.starts_with(&lang)
-                                    && locale_path.join(entry_name).join(&mo_rel_path).exists();
-                            }
+        let locales = match selected_language {
+            Some(ref language) => locales_for(language),
+            None => vec![req_locale],
+        };
+
+        Ok(TextDomainResolution {
+            locales,
+            languages,
+            searched_paths,
+            domain,
+            extra_domains,
+        })
+    }
+
+    fn is_c_locale(&self) -> bool {
+        matches!(self.locale.as_deref(), Some("C") | Some("POSIX"))
+    }
+}
+
+impl DomainResolution {
+    /// A resolution for the "C" and "POSIX" locales, which don't need translations.
+    fn untranslated(domainname: &str) -> DomainResolution {
+        DomainResolution {
+            domainname: domainname.to_owned(),
+            candidates: vec![],
+            language: None,
+            directory: None,
+            catalog: None,
+        }
+    }
+
+    /// Searches the paths for a translation of the domain into each of the languages in turn. A
+    /// language whose translation is found is only selected if `usable` returns `true` for it.
+    fn search<F>(
+        domainname: &str,
+        languages: &[String],
+        searched_paths: &[PathBuf],
+        mut usable: F,
+    ) -> Self
+    where
+        F: FnMut(&str) -> bool,
+    {
+        let mo_rel_path = PathBuf::from("LC_MESSAGES").join(format!("{}.mo", domainname));
+
+        let mut candidates = vec![];
+        let found = languages.iter().find_map(|language| {
+            searched_paths
+                .iter()
+                .find_map(|path| {
+                    find_translation(path, language, &mo_rel_path, &mut candidates)
+                        .map(|catalog| (language.clone(), path.join("locale"), catalog))
+                })
+                .filter(|_| usable(language))
+        });
+
+        let (language, directory, catalog) = match found {
+            Some((language, directory, catalog)) => {
+                debug!(
+                    "using catalog {:?} for domain {:?}, language {:?}",
+                    catalog, domainname, language
+                );
+                (Some(language), Some(directory), Some(catalog))
+            }
+            None => {
+                debug!("no catalog found for domain {:?}", domainname);
+                (None, None, None)
+            }
+        };
+        DomainResolution {
+            domainname: domainname.to_owned(),
+            candidates,
+            language,
+            directory,
+            catalog,
+        }
+    }
+}
+
+/// Returns the languages to search translations for when no locale was requested explicitly.
+///
+/// Like libintl, this honors the `LANGUAGE` environment variable, a colon-separated list of
+/// languages in order of preference (e.g. "de_CH:de:en"), unless the current locale is "C" or
+/// "POSIX". Otherwise, the language of the current locale is used.
+fn get_language_priority_list() -> Vec<String> {
+    language_priority_list(
+        current_locale_language(),
+        env::var("LANGUAGE").ok().as_deref(),
+    )
+}
+
+/// Returns the language of the current locale as a locale name, e.g. "de_CH", or an empty string
+/// for the "C" and "POSIX" locales.
+fn current_locale_language() -> String {
+    Locale::current()
+        .tags_for("messages")
+        .next()
+        .map(|tag| posix_locale_name(tag.as_ref()))
+        .unwrap_or_default()
+}
+
+fn language_priority_list(current: String, language_var: Option<&str>) -> Vec<String> {
+    // `locale_config` reports "C" and "POSIX" locales as an empty tag
+    if !current.is_empty() {
+        let languages: Vec<String> = language_var
+            .unwrap_or_default()
+            .split(':')
+            .filter(|language| !language.is_empty())
+            .map(|language| language.to_owned())
+            .collect();
+        if !languages.is_empty() {
+            return languages;
+        }
+    }
+
+    vec![current]
+}
+
+/// Returns the language part of a locale name or a language tag, e.g. "de" for "de_CH.UTF-8".
+fn language_code(language: &str) -> &str {
+    language
+        .split(&['_', '-', '.', '@'][..])
+        .next()
+        .unwrap_or_default()
+}
 
-                            false
-                        });
+/// Returns the locales that [`TextDomain::init`] tries to switch to for a language selected from
+/// [`TextDomain::languages`], in order. `req_locale` is the locale requested with
+/// [`TextDomain::locale`] (or "" for the current one) and `req_language` its language.
+///
+/// Any locale of the same language would do, but one of another language would make libintl
+/// translate into that language instead. So the requested locale comes first if it's for the
+/// same language, followed by the installed locales of the language. If the installed locales
+/// couldn't be listed, the usual names of the language's locales are guessed instead.
+fn locales_for_language(
+    language: &str,
+    req_locale: &str,
+    req_language: &str,
+    installed: Option<&[String]>,
+) -> Vec<String> {
+    let mut locales = vec![];
+    if !req_language.is_empty() && language_code(req_language) == language_code(language) {
+        locales.push(req_locale.to_owned());
+    }
+    match installed {
+        Some(installed) => locales.extend(installed_locale_names(language, installed)),
+        None => locales.extend(language_locale_names(language)),
+    }
+    locales
+}
+
+/// Returns the names of the installed locales, as listed by `locale -a`, or `None` if they can't
+/// be listed.
+fn installed_locales() -> Option<Vec<String>> {
+    let output = Command::new("locale").arg("-a").output().ok()?;
+    if !output.status.success() {
+        debug!("`locale -a` failed: {:?}", output.status);
+        return None;
+    }
+    let names = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_owned)
+        .collect();
+    Some(names)
+}
+
+/// Returns the installed locales that are for `language`, e.g. "de_DE.utf8" and "de_AT.utf8" for
+/// "de". The territory, codeset and modifier have to match if `language` has them. UTF-8 locales
+/// come first, then the one of the language's main territory (e.g. "de_DE" for "de").
+fn installed_locale_names(language: &str, installed: &[String]) -> Vec<String> {
+    let (code, territory, codeset, modifier) = split_locale_name(language);
+    let codeset = codeset.map(normalize_codeset);
+    let mut names: Vec<String> = installed
+        .iter()
+        .filter(|name| {
+            let (name_code, name_territory, name_codeset, name_modifier) = split_locale_name(name);
+            name_code == code
+                && territory.is_none_or(|territory| name_territory == Some(territory))
+                && (codeset.is_none()
+                    || codeset.as_deref() == name_codeset.map(normalize_codeset).as_deref())
+                && name_modifier == modifier
+        })
+        .cloned()
+        .collect();
+    names.sort_by_cached_key(|name| {
+        let (name_code, name_territory, name_codeset, _) = split_locale_name(name);
+        (
+            name_codeset.map(normalize_codeset).as_deref() != Some("utf8"),
+            !name_territory.is_some_and(|territory| territory.eq_ignore_ascii_case(name_code)),
+            name.clone(),
+        )
+    });
+    names
+}
+
+/// Returns the names of the locales that [`TextDomain::init`] tries to switch to for a language
+/// selected from [`TextDomain::languages`] if the installed locales can't be listed, e.g.
+/// "de_CH.UTF-8", "de_CH.utf8", and "de_CH" for "de_CH".
+fn language_locale_names(language: &str) -> Vec<String> {
+    if language.contains('.') {
+        vec![language.to_owned()]
+    } else {
+        let (name, modifier) = match language.find('@') {
+            Some(index) => language.split_at(index),
+            None => (language, ""),
+        };
+        vec![
+            format!("{}.UTF-8{}", name, modifier),
+            format!("{}.utf8{}", name, modifier),
+            language.to_owned(),
+        ]
+    }
+}
+
+/// Converts a language tag like "pt-BR" into a locale name like "pt_BR", which is how locale
+/// directories are named. Locale names are returned unchanged.
+pub(crate) fn posix_locale_name(language: &str) -> String {
+    if language.contains(&['_', '.', '@'][..]) {
+        return language.to_owned();
+    }
+
+    let mut subtags = language.split('-');
+    let mut name = subtags.next().unwrap_or_default().to_owned();
+    let mut modifier = None;
+    for subtag in subtags {
+        let is_region = (subtag.len() == 2 && subtag.chars().all(|c| c.is_ascii_alphabetic()))
+            || (subtag.len() == 3 && subtag.chars().all(|c| c.is_ascii_digit()));
+        if is_region {
+            name.push('_');
+            name.push_str(&subtag.to_ascii_uppercase());
+        } else if subtag.eq_ignore_ascii_case("Latn") {
+            modifier = Some("latin");
+        } else if subtag.eq_ignore_ascii_case("Cyrl") {
+            modifier = Some("cyrillic");
+        }
+    }
+    if let Some(modifier) = modifier {
+        name.push('@');
+        name.push_str(modifier);
+    }
+
+    name
+}
+
+/// Normalizes a codeset name the way libintl does: only letters and digits are kept, letters are
+/// lowercased, and "iso" is prepended to all-digit names. E.g. "UTF-8" becomes "utf8".
+fn normalize_codeset(codeset: &str) -> String {
+    let normalized: String = codeset
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+
+    if normalized.chars().all(|c| c.is_ascii_digit()) {
+        format!("iso{}", normalized)
+    } else {
+        normalized
+    }
+}
+
+/// Returns the names of the locale directories that may hold a translation for `locale`, from the
+/// most specific to the least specific one. The order is the same as libintl's: for
+/// "de_CH.UTF-8@euro" it's "de_CH.UTF-8@euro", "de_CH.utf8@euro", "de_CH@euro", "de.UTF-8@euro",
+/// "de.utf8@euro", "de@euro", "de_CH.UTF-8", "de_CH.utf8", "de_CH", "de.UTF-8", "de.utf8", "de".
+pub(crate) fn locale_dir_names(locale: &str) -> Vec<String> {
+    let (language, territory, codeset, modifier) = split_locale_name(locale);
+    if language.is_empty() {
+        return vec![];
+    }
+
+    let normalized_codeset = codeset.map(normalize_codeset);
+    let mut codesets = vec![codeset];
+    if normalized_codeset.as_deref() != codeset {
+        codesets.push(normalized_codeset.as_deref());
+    }
+    codesets.push(None);
+
+    let mut names = vec![];
+    for modifier in &[modifier, None] {
+        for territory in &[territory, None] {
+            for codeset in &codesets {
+                let mut name = language.to_owned();
+                if let Some(territory) = territory {
+                    name.push('_');
+                    name.push_str(territory);
+                }
+                if let Some(codeset) = codeset {
+                    name.push('.');
+                    name.push_str(codeset);
                 }
+                if let Some(modifier) = modifier {
+                    name.push('@');
+                    name.push_str(modifier);
+                }
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+    }
 
-                false
-            })
-            .map_or(Err(TextDomainError::TranslationNotFound(lang)), |path| {
-                let result = setlocale(locale_category, req_locale);
-                bindtextdomain(domainname.clone(), path.join("locale"))
-                    .map_err(TextDomainError::BindTextDomainCallFailed)?;
-                bind_textdomain_codeset(domainname.clone(), codeset)
-                    .map_err(TextDomainError::BindTextDomainCodesetCallFailed)?;
-                textdomain(domainname).map_err(TextDomainError::TextDomainCallFailed)?;
-                Ok(result)
-            })
+    names
+}
+
+/// Splits a locale name like "de_CH.UTF-8@euro" into the language, territory, codeset and
+/// modifier.
+fn split_locale_name(locale: &str) -> (&str, Option<&str>, Option<&str>, Option<&str>) {
+    fn split_off(name: &str, separator: char) -> (&str, Option<&str>) {
+        let mut parts = name.splitn(2, separator);
+        (parts.next().unwrap_or_default(), parts.next())
+    }
+
+    let (rest, modifier) = split_off(locale, '@');
+    let (rest, codeset) = split_off(rest, '.');
+    let (language, territory) = split_off(rest, '_');
+    (language, territory, codeset, modifier)
+}
+
+/// Looks for a translation file for the text domain in the given locale under `path`, i.e.
+/// `path/locale/name/mo_rel_path` where `name` is one of the [`locale_dir_names`]. Every locale
+/// directory that is checked is added to `candidates`.
+fn find_translation(
+    path: &Path,
+    locale: &str,
+    mo_rel_path: &Path,
+    candidates: &mut Vec<PathBuf>,
+) -> Option<PathBuf> {
+    let locale_path = path.join("locale");
+    if !locale_path.is_dir() {
+        debug!("rejecting {:?}: not a directory", locale_path);
+        return None;
+    }
+
+    locale_dir_names(locale).iter().find_map(|name| {
+        let candidate = locale_path.join(name);
+        let catalog = candidate.join(mo_rel_path);
+        candidates.push(candidate);
+        if catalog.is_file() {
+            Some(catalog)
+        } else {
+            debug!("rejecting {:?}: not a file", catalog);
+            None
+        }
+    })
+}
+
+/// A path to search translations in, as given to the [`TextDomain`] builder.
+#[derive(Debug)]
+enum SearchPath {
+    /// A path that is used as is.
+    Fixed(PathBuf),
+    /// A path relative to the directory of the running executable.
+    ExeRelative(PathBuf),
+}
+
+impl SearchPath {
+    /// Returns the path to search, or `None` if an executable-relative path can't be resolved.
+    fn resolve(&self) -> Option<PathBuf> {
+        match self {
+            SearchPath::Fixed(path) => Some(path.clone()),
+            SearchPath::ExeRelative(path) => get_exe_dir().map(|dir| dir.join(path)),
+        }
+    }
+}
+
+/// Returns the directory containing the running executable, with symlinks resolved.
+fn get_exe_dir() -> Option<PathBuf> {
+    let exe = env::current_exe().ok()?.canonicalize().ok()?;
+    exe.parent().map(Path::to_path_buf)
+}
+
+fn get_user_data_path() -> Option<PathBuf> {
+    // The XDG base directory specification says relative paths are invalid and should be ignored
+    match env::var_os("XDG_DATA_HOME").map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => Some(dir),
+        _ => env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")),
     }
 }
 
@@ -406,6 +1216,7 @@ impl fmt::Debug for TextDomain {
         let mut debug_struct = fmt.debug_struct("TextDomain");
         debug_struct
             .field("domainname", &self.domainname)
+            .field("extra_domains", &self.extra_domains)
             .field(
                 "locale",
                 &match self.locale.as_ref() {
@@ -416,11 +1227,15 @@ impl fmt::Debug for TextDomain {
                     }
                 },
             )
+            .field("languages", &self.languages)
             .field("locale_category", &self.locale_category)
             .field("codeset", &self.codeset)
             .field("pre_paths", &self.pre_paths);
 
         if !self.skip_system_data_paths {
+            if !self.skip_user_data_path {
+                debug_struct.field("using user data path", &get_user_data_path());
+            }
             debug_struct.field("using system data paths", &get_system_data_paths());
         }
 
@@ -430,7 +1245,14 @@ impl fmt::Debug for TextDomain {
 
 #[cfg(test)]
 mod tests {
-    use super::{LocaleCategory, TextDomain, TextDomainError};
+    use super::{
+        find_translation, installed_locale_names, language_code, language_locale_names,
+        language_priority_list, locale_dir_names, locales_for_language, normalize_codeset,
+        posix_locale_name, LocaleCategory, SearchPath, TextDomain, TextDomainError,
+    };
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn errors() {
@@ -440,7 +1262,9 @@ mod tests {
         };
 
         match TextDomain::new("0_0").locale("en_US").init().err() {
-            Some(TextDomainError::TranslationNotFound(message)) => assert_eq!(message, "en"),
+            Some(TextDomainError::TranslationNotFound { language, .. }) => {
+                assert_eq!(language, "en")
+            }
             _ => panic!(),
         };
     }
@@ -449,12 +1273,16 @@ mod tests {
     fn attributes() {
         let text_domain = TextDomain::new("test");
         assert_eq!("test".to_owned(), text_domain.domainname);
+        assert!(text_domain.extra_domains.is_empty());
         assert!(text_domain.locale.is_none());
+        assert!(text_domain.languages.is_empty());
         assert_eq!(LocaleCategory::LcMessages, text_domain.locale_category);
         assert_eq!(text_domain.codeset, "UTF-8");
         assert!(text_domain.pre_paths.is_empty());
         assert!(text_domain.post_paths.is_empty());
         assert!(!text_domain.skip_system_data_paths);
+        assert!(!text_domain.skip_user_data_path);
+        assert!(!text_domain.ignore_missing_translations);
 
         let text_domain = text_domain.locale_category(LocaleCategory::LcAll);
         assert_eq!(LocaleCategory::LcAll, text_domain.locale_category);
@@ -468,16 +1296,550 @@ mod tests {
         let text_domain = text_domain.push("post");
         assert!(!text_domain.post_paths.is_empty());
 
+        let text_domain = text_domain.prepend_exe_relative("../share");
+        assert_eq!(text_domain.pre_paths.len(), 2);
+
+        let text_domain = text_domain.push_exe_relative("../share");
+        assert_eq!(text_domain.post_paths.len(), 2);
+
         let text_domain = text_domain.skip_system_data_paths();
         assert!(text_domain.skip_system_data_paths);
 
+        let text_domain = text_domain.skip_user_data_path();
+        assert!(text_domain.skip_user_data_path);
+
+        let text_domain = text_domain.ignore_missing_translations();
+        assert!(text_domain.ignore_missing_translations);
+
         let text_domain = TextDomain::new("test").locale("en_US");
         assert_eq!(Some("en_US".to_owned()), text_domain.locale);
 
+        let text_domain = TextDomain::new("test").languages(["de_CH", "", "pt-BR"]);
+        assert_eq!(vec!["de_CH", "pt_BR"], text_domain.languages);
+
         // accept locale, but fail to find translation
         match TextDomain::new("0_0").locale("en_US").init().err() {
-            Some(TextDomainError::TranslationNotFound(message)) => assert_eq!(message, "en"),
+            Some(TextDomainError::TranslationNotFound { language, .. }) => {
+                assert_eq!(language, "en")
+            }
             _ => panic!(),
         };
     }
+
+    #[test]
+    fn language_codes() {
+        assert_eq!(language_code("de"), "de");
+        assert_eq!(language_code("de_CH"), "de");
+        assert_eq!(language_code("pt-BR"), "pt");
+        assert_eq!(language_code("sr_RS.UTF-8@latin"), "sr");
+        assert_eq!(language_code("ca@valencia"), "ca");
+        assert_eq!(language_code(""), "");
+    }
+
+    #[test]
+    fn language_priority() {
+        assert_eq!(
+            language_priority_list("en-US".to_owned(), Some("de_CH:de::en")),
+            vec!["de_CH", "de", "en"]
+        );
+        assert_eq!(
+            language_priority_list("en-US".to_owned(), Some("")),
+            vec!["en-US"]
+        );
+        assert_eq!(
+            language_priority_list("en-US".to_owned(), None),
+            vec!["en-US"]
+        );
+
+        // libintl ignores `LANGUAGE` in the "C" locale
+        assert_eq!(
+            language_priority_list("".to_owned(), Some("de_CH:de")),
+            vec![""]
+        );
+    }
+
+    #[test]
+    fn locale_names() {
+        assert_eq!(posix_locale_name("pt-BR"), "pt_BR");
+        assert_eq!(posix_locale_name("en-us"), "en_US");
+        assert_eq!(posix_locale_name("es-419"), "es_419");
+        assert_eq!(posix_locale_name("sr-Latn-RS"), "sr_RS@latin");
+        assert_eq!(posix_locale_name("de"), "de");
+        assert_eq!(posix_locale_name("de_CH.UTF-8"), "de_CH.UTF-8");
+
+        assert_eq!(normalize_codeset("UTF-8"), "utf8");
+        assert_eq!(normalize_codeset("ISO_8859-1"), "iso88591");
+        assert_eq!(normalize_codeset("8859-1"), "iso88591");
+    }
+
+    #[test]
+    fn locale_dir_order() {
+        assert_eq!(
+            locale_dir_names("de_CH.UTF-8@euro"),
+            vec![
+                "de_CH.UTF-8@euro",
+                "de_CH.utf8@euro",
+                "de_CH@euro",
+                "de.UTF-8@euro",
+                "de.utf8@euro",
+                "de@euro",
+                "de_CH.UTF-8",
+                "de_CH.utf8",
+                "de_CH",
+                "de.UTF-8",
+                "de.utf8",
+                "de",
+            ]
+        );
+        assert_eq!(
+            locale_dir_names("pt_BR.utf8"),
+            vec!["pt_BR.utf8", "pt_BR", "pt.utf8", "pt"]
+        );
+        assert_eq!(locale_dir_names("pt"), vec!["pt"]);
+        assert!(locale_dir_names("").is_empty());
+    }
+
+    fn make_locale_tree(name: &str, locales: &[&str]) -> PathBuf {
+        let root = env::temp_dir().join(format!("gettext-rs-{}-{}", name, std::process::id()));
+        for locale in locales {
+            let dir = root.join("locale").join(locale).join("LC_MESSAGES");
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("test.mo"), b"").unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn locale_dir_matching() {
+        let root = make_locale_tree("locale_dir_matching", &["pt_BR", "de"]);
+        let mo_rel_path = Path::new("LC_MESSAGES").join("test.mo");
+        let mut candidates = vec![];
+
+        // `pt` must not pick up the `pt_BR` catalog
+        assert_eq!(
+            find_translation(&root, "pt", &mo_rel_path, &mut candidates),
+            None
+        );
+        assert_eq!(candidates, vec![root.join("locale").join("pt")]);
+        assert_eq!(
+            find_translation(&root, "pt_BR", &mo_rel_path, &mut candidates),
+            Some(root.join("locale/pt_BR").join(&mo_rel_path))
+        );
+        assert_eq!(
+            find_translation(&root, "pt_BR.UTF-8", &mo_rel_path, &mut candidates),
+            Some(root.join("locale/pt_BR").join(&mo_rel_path))
+        );
+        // ...but less specific catalogs are fine
+        assert_eq!(
+            find_translation(&root, "de_CH.UTF-8", &mo_rel_path, &mut candidates),
+            Some(root.join("locale/de").join(&mo_rel_path))
+        );
+        assert_eq!(
+            find_translation(&root, "fr", &mo_rel_path, &mut candidates),
+            None
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    /// Installed locales for the tests, so that they don't depend on the ones of the machine.
+    fn installed() -> Vec<String> {
+        [
+            "C",
+            "C.utf8",
+            "POSIX",
+            "de_AT.utf8",
+            "de_CH.utf8",
+            "de_DE",
+            "de_DE.utf8",
+            "en_US.utf8",
+            "fr_CA.utf8",
+            "fr_FR.utf8",
+            "sr_RS.utf8@latin",
+        ]
+        .iter()
+        .map(|name| name.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn resolve() {
+        let root = make_locale_tree("resolve", &["de"]);
+
+        let resolution = TextDomain::new("test")
+            .skip_system_data_paths()
+            .push(&root)
+            .locale("en_US.UTF-8")
+            .languages(["fr", "de_CH"])
+            .resolve_with(Some(&installed()))
+            .unwrap();
+        assert_eq!(resolution.domain.domainname, "test");
+        assert_eq!(resolution.languages, vec!["fr", "de_CH"]);
+        assert_eq!(resolution.searched_paths, vec![root.clone()]);
+        assert_eq!(
+            resolution.domain.candidates,
+            vec![
+                root.join("locale/fr"),
+                root.join("locale/de_CH"),
+                root.join("locale/de"),
+            ]
+        );
+        assert_eq!(resolution.domain.language.as_deref(), Some("de_CH"));
+        assert_eq!(resolution.domain.directory, Some(root.join("locale")));
+        assert_eq!(
+            resolution.domain.catalog,
+            Some(root.join("locale/de/LC_MESSAGES/test.mo"))
+        );
+        assert_eq!(resolution.locales, vec!["de_CH.utf8"]);
+
+        let resolution = TextDomain::new("test")
+            .skip_system_data_paths()
+            .push(&root)
+            .locale("de_AT.UTF-8")
+            .resolve()
+            .unwrap();
+        assert_eq!(resolution.locales, vec!["de_AT.UTF-8"]);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn language_locales() {
+        assert_eq!(
+            language_locale_names("de_CH"),
+            vec!["de_CH.UTF-8", "de_CH.utf8", "de_CH"]
+        );
+        assert_eq!(
+            language_locale_names("sr_RS@latin"),
+            vec!["sr_RS.UTF-8@latin", "sr_RS.utf8@latin", "sr_RS@latin"]
+        );
+        assert_eq!(
+            language_locale_names("de_DE.ISO-8859-1"),
+            vec!["de_DE.ISO-8859-1"]
+        );
+    }
+
+    #[test]
+    fn language_locales_from_installed() {
+        let installed = installed();
+        assert_eq!(
+            installed_locale_names("de", &installed),
+            vec!["de_DE.utf8", "de_AT.utf8", "de_CH.utf8", "de_DE"]
+        );
+        assert_eq!(
+            installed_locale_names("de_CH", &installed),
+            vec!["de_CH.utf8"]
+        );
+        assert_eq!(
+            installed_locale_names("de_DE.ISO-8859-1", &installed),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            installed_locale_names("de_DE.UTF-8", &installed),
+            vec!["de_DE.utf8"]
+        );
+        assert_eq!(
+            installed_locale_names("sr@latin", &installed),
+            vec!["sr_RS.utf8@latin"]
+        );
+        assert_eq!(
+            installed_locale_names("sr", &installed),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            installed_locale_names("it", &installed),
+            Vec::<String>::new()
+        );
+
+        // The requested locale comes first if it's for the same language
+        assert_eq!(
+            locales_for_language("de_CH", "de_AT.UTF-8", "de_AT.UTF-8", Some(&installed)),
+            vec!["de_AT.UTF-8", "de_CH.utf8"]
+        );
+        assert_eq!(
+            locales_for_language("fr", "", "de_AT", Some(&installed)),
+            vec!["fr_FR.utf8", "fr_CA.utf8"]
+        );
+        assert_eq!(
+            locales_for_language("fr", "", "", None),
+            vec!["fr.UTF-8", "fr.utf8", "fr"]
+        );
+    }
+
+    #[test]
+    fn languages_without_locale() {
+        let root = make_locale_tree("languages_without_locale", &["de", "fr"]);
+        let en_us = |installed: &[String]| {
+            TextDomain::new("test")
+                .skip_system_data_paths()
+                .push(&root)
+                .locale("en_US.UTF-8")
+                .languages(["de_CH", "de", "fr"])
+                .resolve_with(Some(installed))
+        };
+
+        // The catalog for "de" is only used if there is a German locale to switch to
+        let resolution = en_us(&installed()).unwrap();
+        assert_eq!(resolution.domain.language.as_deref(), Some("de_CH"));
+        assert_eq!(resolution.locales, vec!["de_CH.utf8"]);
+
+        let without_de_ch: Vec<String> = installed()
+            .into_iter()
+            .filter(|name| !name.starts_with("de_CH"))
+            .collect();
+        let resolution = en_us(&without_de_ch).unwrap();
+        assert_eq!(resolution.domain.language.as_deref(), Some("de"));
+        assert_eq!(
+            resolution.locales,
+            vec!["de_DE.utf8", "de_AT.utf8", "de_DE"]
+        );
+
+        let without_de: Vec<String> = installed()
+            .into_iter()
+            .filter(|name| !name.starts_with("de_"))
+            .collect();
+        let resolution = en_us(&without_de).unwrap();
+        assert_eq!(resolution.domain.language.as_deref(), Some("fr"));
+        assert_eq!(resolution.locales, vec!["fr_FR.utf8", "fr_CA.utf8"]);
+        assert_eq!(
+            resolution.domain.catalog,
+            Some(root.join("locale/fr/LC_MESSAGES/test.mo"))
+        );
+
+        // There are translations, but no locale for any of their languages
+        let c_only = vec!["C".to_owned(), "POSIX".to_owned()];
+        match en_us(&c_only).err() {
+            Some(TextDomainError::SetLocaleFailed { locale, category }) => {
+                assert_eq!(locale, "de_CH");
+                assert_eq!(category, LocaleCategory::LcMessages);
+            }
+            _ => panic!(),
+        };
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn languages_not_found() {
+        match TextDomain::new("0_0")
+            .skip_system_data_paths()
+            .languages(["de_CH", "fr"])
+            .init()
+            .err()
+        {
+            Some(TextDomainError::TranslationNotFound { language, .. }) => {
+                assert_eq!(language, "de:fr")
+            }
+            _ => panic!(),
+        };
+    }
+
+    #[test]
+    fn exe_relative_paths() {
+        let exe_dir = env::current_exe()
+            .unwrap()
+            .canonicalize()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .to_path_buf();
+
+        assert_eq!(
+            SearchPath::ExeRelative(PathBuf::from("../share")).resolve(),
+            Some(exe_dir.join("../share"))
+        );
+        assert_eq!(
+            SearchPath::Fixed(PathBuf::from("/opt/share")).resolve(),
+            Some(PathBuf::from("/opt/share"))
+        );
+    }
+
+    #[test]
+    fn extra_domains() {
+        let root = make_locale_tree("extra_domains", &["de", "fr"]);
+        fs::write(root.join("locale/fr/LC_MESSAGES/library.mo"), b"").unwrap();
+
+        let resolution = TextDomain::new("test")
+            .extra_domain("library")
+            .skip_system_data_paths()
+            .push(&root)
+            .languages(["fr", "de"])
+            .resolve_with(Some(&installed()))
+            .unwrap();
+        assert_eq!(resolution.domain.language.as_deref(), Some("fr"));
+        assert_eq!(resolution.extra_domains.len(), 1);
+        assert_eq!(resolution.extra_domains[0].domainname, "library");
+        assert_eq!(resolution.extra_domains[0].language.as_deref(), Some("fr"));
+        assert_eq!(
+            resolution.extra_domains[0].catalog,
+            Some(root.join("locale/fr/LC_MESSAGES/library.mo"))
+        );
+
+        // libintl would look "library" up in German too, so the French translation doesn't count
+        match TextDomain::new("test")
+            .extra_domain("library")
+            .skip_system_data_paths()
+            .push(&root)
+            .languages(["de", "fr"])
+            .resolve_with(Some(&installed()))
+            .err()
+        {
+            Some(TextDomainError::TranslationNotFound {
+                language,
+                domainname,
+                ..
+            }) => {
+                assert_eq!(language, "de:fr");
+                assert_eq!(domainname, "library");
+            }
+            _ => panic!(),
+        };
+
+        match TextDomain::new("test")
+            .extra_domain("missing")
+            .skip_system_data_paths()
+            .push(&root)
+            .languages(["de"])
+            .resolve_with(Some(&installed()))
+            .err()
+        {
+            Some(TextDomainError::TranslationNotFound {
+                language,
+                domainname,
+                searched_paths,
+                candidates,
+            }) => {
+                assert_eq!(language, "de");
+                assert_eq!(domainname, "missing");
+                assert_eq!(searched_paths, vec![root.clone()]);
+                assert_eq!(candidates, vec![root.join("locale/de")]);
+            }
+            _ => panic!(),
+        };
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn missing_translations() {
+        let root = make_locale_tree("missing_translations", &["de"]);
+
+        let resolution = TextDomain::new("test")
+            .extra_domain("library")
+            .skip_system_data_paths()
+            .push(&root)
+            .languages(["de"])
+            .ignore_missing_translations()
+            .resolve_with(Some(&installed()))
+            .unwrap();
+        assert!(resolution.domain.catalog.is_some());
+        assert_eq!(resolution.extra_domains[0].catalog, None);
+        assert_eq!(resolution.extra_domains[0].directory, None);
+
+        let resolution = TextDomain::new("0_0")
+            .skip_system_data_paths()
+            .locale("en_US")
+            .ignore_missing_translations()
+            .resolve()
+            .unwrap();
+        assert_eq!(resolution.locales, vec!["en_US"]);
+        assert_eq!(resolution.domain.language, None);
+        assert_eq!(resolution.domain.catalog, None);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn locale_and_languages() {
+        let root = make_locale_tree("locale_and_languages", &["fr", "xx"]);
+
+        // A German locale would make libintl translate into German
+        let resolution = TextDomain::new("test")
+            .skip_system_data_paths()
+            .push(&root)
+            .locale("de_AT.UTF-8")
+            .languages(["de", "fr"])
+            .resolve_with(Some(&installed()))
+            .unwrap();
+        assert_eq!(resolution.domain.language.as_deref(), Some("fr"));
+        assert_eq!(resolution.locales, vec!["fr_FR.utf8", "fr_CA.utf8"]);
+
+        let resolution = TextDomain::new("test")
+            .skip_system_data_paths()
+            .push(&root)
+            .locale("fr_CA.UTF-8")
+            .languages(["de", "fr"])
+            .resolve_with(Some(&installed()))
+            .unwrap();
+        assert_eq!(
+            resolution.locales,
+            vec!["fr_CA.UTF-8", "fr_FR.utf8", "fr_CA.utf8"]
+        );
+
+        // There is no "xx" locale to switch to, and the environment isn't changed instead
+        let language_var = env::var_os("LANGUAGE");
+        match TextDomain::new("test")
+            .skip_system_data_paths()
+            .push(&root)
+            .locale("de_AT.UTF-8")
+            .languages(["xx"])
+            .init()
+            .err()
+        {
+            Some(TextDomainError::SetLocaleFailed { locale, .. }) => {
+                assert!(locale.starts_with("xx"), "{}", locale)
+            }
+            _ => panic!(),
+        };
+        assert_eq!(env::var_os("LANGUAGE"), language_var);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn set_locale_failed() {
+        let root = make_locale_tree("set_locale_failed", &["xx_YY"]);
+
+        match TextDomain::new("test")
+            .skip_system_data_paths()
+            .push(&root)
+            .locale("xx_YY.UTF-8")
+            .init()
+            .err()
+        {
+            Some(TextDomainError::SetLocaleFailed { locale, category }) => {
+                assert_eq!(locale, "xx_YY.UTF-8");
+                assert_eq!(category, LocaleCategory::LcMessages);
+            }
+            _ => panic!(),
+        };
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn c_locale() {
+        use getters::{current_textdomain, textdomain_codeset};
+
+        let resolution = TextDomain::new("c_locale")
+            .locale("POSIX")
+            .resolve()
+            .unwrap();
+        assert_eq!(resolution.locales, vec!["POSIX"]);
+        assert!(resolution.searched_paths.is_empty());
+        assert_eq!(resolution.domain.catalog, None);
+
+        let _config = ::GettextConfig::lock();
+        let init = TextDomain::new("c_locale")
+            .extra_domain("c_locale_library")
+            .locale("C")
+            .init()
+            .unwrap();
+        assert_eq!(init.locale, b"C");
+        assert_eq!(current_textdomain().unwrap(), b"c_locale");
+        assert_eq!(
+            textdomain_codeset("c_locale_library").unwrap().as_deref(),
+            Some("UTF-8")
+        );
+    }
 }
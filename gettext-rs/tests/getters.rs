@@ -1,24 +1,17 @@
 extern crate gettextrs;
-#[macro_use]
-extern crate lazy_static;
 
 use gettextrs::{getters::*, *};
-use std::sync::Mutex;
-
-lazy_static! {
-    // "Current text domain" is a global resource which all tests modify. This mutex serializes
-    // access to that resource.
-    static ref TEXTDOMAIN_MUTEX: Mutex<()> = Mutex::new(());
-}
 
 #[test]
 fn test_current_textdomain() {
-    let _lock = TEXTDOMAIN_MUTEX.lock().unwrap();
+    // "Current text domain" is a global resource which all tests modify, so other tests shouldn't
+    // change it between our calls.
+    let config = GettextConfig::lock();
 
-    textdomain("just_testing").unwrap();
+    config.textdomain("just_testing").unwrap();
     assert_eq!(current_textdomain().unwrap(), "just_testing".as_bytes());
 
-    textdomain("test_current_textdomain").unwrap();
+    config.textdomain("test_current_textdomain").unwrap();
     assert_eq!(
         current_textdomain().unwrap(),
         "test_current_textdomain".as_bytes()
@@ -31,10 +24,7 @@ fn test_domain_directory() {
 
     static TEXTDOMAIN: &'static str = "test_domain_directory";
 
-    {
-        let _lock = TEXTDOMAIN_MUTEX.lock().unwrap();
-        textdomain(TEXTDOMAIN).unwrap();
-    }
+    textdomain(TEXTDOMAIN).unwrap();
 
     bindtextdomain(TEXTDOMAIN, "/tmp").unwrap();
     assert_eq!(domain_directory(TEXTDOMAIN).unwrap(), PathBuf::from("/tmp"));
@@ -48,10 +38,7 @@ fn test_domain_directory() {
 fn test_textdomain_codeset() {
     static TEXTDOMAIN: &'static str = "test_textdomain_codeset";
 
-    {
-        let _lock = TEXTDOMAIN_MUTEX.lock().unwrap();
-        textdomain(TEXTDOMAIN).unwrap();
-    }
+    textdomain(TEXTDOMAIN).unwrap();
 
     bind_textdomain_codeset(TEXTDOMAIN, "C").unwrap();
     assert_eq!(
@@ -65,3 +52,22 @@ fn test_textdomain_codeset() {
         Some("UTF-8".to_string())
     );
 }
+
+#[test]
+fn test_catalog_metadata_without_catalog() {
+    static TEXTDOMAIN: &'static str = "test_catalog_metadata_without_catalog";
+
+    bindtextdomain(TEXTDOMAIN, "/some/nonexistent path (hopefully)").unwrap();
+    assert_eq!(catalog_metadata(TEXTDOMAIN), None);
+}
+
+#[test]
+fn test_libintl_version() {
+    match libintl_implementation() {
+        LibintlImplementation::Glibc | LibintlImplementation::GnuLibintl => {
+            let version = libintl_version().unwrap();
+            assert!(version.split('.').all(|part| part.parse::<u32>().is_ok()));
+        }
+        LibintlImplementation::Other => assert_eq!(libintl_version(), None),
+    }
+}
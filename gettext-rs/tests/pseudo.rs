@@ -0,0 +1,72 @@
+extern crate gettextrs;
+
+use gettextrs::*;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const HEADER: &str = "Content-Type: text/plain; charset=UTF-8\nPlural-Forms: nplurals=3; \
+                      plural=(n==1 ? 0 : n==2 ? 1 : 2);\n";
+
+/// Write an MO file for `domainname` in the language "xx" that has nothing but a header.
+fn write_catalog(domainname: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("gettext-rs-pseudo-{}", std::process::id()));
+    let messages_dir = dir.join("xx").join("LC_MESSAGES");
+    fs::create_dir_all(&messages_dir).unwrap();
+
+    // Magic number, revision, number of messages, offsets of the two string tables, no hash table
+    let mut mo = Vec::new();
+    for word in &[0x9504_12de_u32, 0, 1, 28, 36, 0, 0] {
+        mo.extend_from_slice(&word.to_le_bytes());
+    }
+    // The msgid is empty, and the header follows the tables
+    for word in &[0, 44, HEADER.len() as u32, 45] {
+        mo.extend_from_slice(&word.to_le_bytes());
+    }
+    mo.push(0);
+    mo.extend_from_slice(HEADER.as_bytes());
+    mo.push(0);
+    fs::write(messages_dir.join(format!("{}.mo", domainname)), mo).unwrap();
+    dir
+}
+
+// Pseudo-localization affects every lookup in the process, so it's tested in a process of its own,
+// in a single test.
+#[test]
+fn test_pseudolocalization() {
+    let dir = write_catalog("pseudo_test");
+    bindtextdomain("pseudo_test", dir.clone()).unwrap();
+    // libintl doesn't load catalogs in the "C" locale, but does in "C.UTF-8"
+    env::set_var("LANGUAGE", "xx");
+    let locale = ["C.UTF-8", "C.utf8", "en_US.UTF-8"]
+        .iter()
+        .find_map(|locale| setlocale(LocaleCategory::LcMessages, *locale));
+    assert!(locale.is_some(), "no locale to load catalogs in");
+
+    env::set_var("GETTEXTRS_PSEUDOLOCALIZATION", "100");
+    assert_eq!(gettext("Save"), "[Šávé~~~~]");
+    assert_eq!(dgettext("pseudo_test", "Save"), "[Šávé~~~~]");
+    assert_eq!(pgettext("File menu", "Save"), "[Šávé~~~~]");
+    assert_eq!(ngettext("One file", "{} files", 3), "[{} ƒîļéš~~~~~~~~]");
+    assert_eq!(gettext!("Hello, {}!", "Ana"), "[Ĥéļļó, Ana!~~~~~~~~~~]");
+    // Catalog headers are still looked up, e.g. by the cache for the plural forms
+    assert_eq!(dgettext("pseudo_test", ""), HEADER);
+    assert_eq!(
+        dcgettext("pseudo_test", "", LocaleCategory::LcMessages),
+        HEADER
+    );
+
+    disable_pseudolocalization();
+    assert_eq!(gettext("Save"), "Save");
+    assert_eq!(ngettext("One file", "{} files", 3), "{} files");
+
+    enable_pseudolocalization(0);
+    assert_eq!(gettext("Save"), "[Šávé]");
+    assert_eq!(
+        npgettext("File menu", "One file", "{} files", 1),
+        "[Óñé ƒîļé]"
+    );
+
+    disable_pseudolocalization();
+    fs::remove_dir_all(dir).unwrap();
+}
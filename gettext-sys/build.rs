@@ -82,13 +82,133 @@ fn check_dependencies(required_programs: Vec<&str>) {
     }
 }
 
+/// Locale categories that `LocaleCategory` in gettext-rs can refer to.
+const LOCALE_CATEGORIES: &[&str] = &[
+    "LC_CTYPE",
+    "LC_NUMERIC",
+    "LC_TIME",
+    "LC_COLLATE",
+    "LC_MONETARY",
+    "LC_MESSAGES",
+    "LC_ALL",
+    "LC_PAPER",
+    "LC_NAME",
+    "LC_ADDRESS",
+    "LC_TELEPHONE",
+    "LC_MEASUREMENT",
+    "LC_IDENTIFICATION",
+];
+
 fn main() {
     let target = env::var("TARGET").unwrap();
+    let include = link_gettext();
+
+    let mut names = LOCALE_CATEGORIES.to_vec();
+    names.push("LIBINTL_VERSION");
+    let values = expand_macros(include.as_deref(), &names);
+
+    let mut constants =
+        String::from("// Generated by build.rs from the target's locale.h and libintl.h.\n");
+    let mut missing = Vec::new();
+    for (name, value) in LOCALE_CATEGORIES.iter().zip(&values) {
+        let value = value.unwrap_or_else(|| {
+            missing.push(*name);
+            -1
+        });
+        constants.push_str(&format!("pub const {}: c_int = {};\n", name, value));
+    }
+    // Categories that the platform doesn't define are set to -1; systest can't check those
+    println!("cargo:missing_locale_categories={}", missing.join(","));
+
+    // Only GNU libintl has a version number
+    println!("cargo:rustc-check-cfg=cfg(gettext_libintl_version)");
+    if let Some(version) = values[LOCALE_CATEGORIES.len()] {
+        constants.push_str(&format!(
+            "pub const LIBINTL_VERSION: c_int = 0x{:06x};\n",
+            version
+        ));
+        println!("cargo:rustc-cfg=gettext_libintl_version");
+        println!("cargo:libintl_version={}", version);
+    }
+
+    // Both glibc and GNU libintl have the catalog counter, but musl doesn't. (No include directory
+    // means that the system's libintl is used.)
+    println!("cargo:rustc-check-cfg=cfg(gettext_msg_cat_cntr)");
+    if !(include.is_none() && target.contains("musl")) {
+        println!("cargo:rustc-cfg=gettext_msg_cat_cntr");
+        println!("cargo:msg_cat_cntr=1");
+    }
+
+    let constants_path = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("constants.rs");
+    fs::write(&constants_path, constants)
+        .unwrap_or_else(|e| fail(&format!("Couldn't write {:?}: {}", constants_path, e)));
+    println!("cargo:constants={}", constants_path.display());
+}
+
+/// Runs the preprocessor over `locale.h` and `libintl.h` to find the values of the given integer
+/// macros. The value is `None` if the macro isn't defined.
+fn expand_macros(include: Option<&Path>, names: &[&str]) -> Vec<Option<i32>> {
+    // libintl.h goes second because it defines LC_MESSAGES on platforms that lack it
+    let mut probe = String::from("#include <locale.h>\n#include <libintl.h>\n");
+    for name in names {
+        probe.push_str(&format!(
+            "#ifdef {0}\ngettext_sys_{0} = {0};\n#endif\n",
+            name
+        ));
+    }
+    let probe_path = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("probe.c");
+    fs::write(&probe_path, probe)
+        .unwrap_or_else(|e| fail(&format!("Couldn't write {:?}: {}", probe_path, e)));
+
+    let mut cfg = cc::Build::new();
+    cfg.file(&probe_path);
+    if let Some(include) = include {
+        cfg.include(include);
+    }
+    let expanded = cfg
+        .try_expand()
+        .unwrap_or_else(|e| fail(&format!("Couldn't preprocess libintl.h: {}", e)));
+    // Drop the line markers, which can split an expanded macro across several lines
+    let expanded = String::from_utf8_lossy(&expanded)
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    names
+        .iter()
+        .map(|name| {
+            let marker = format!("gettext_sys_{} =", name);
+            let value = expanded
+                .split(';')
+                .find_map(|statement| statement.trim().strip_prefix(&marker))?;
+            let value: String = value
+                .chars()
+                .filter(|c| !c.is_whitespace() && !"()".contains(*c))
+                .collect();
+            let parsed = match value.strip_prefix("0x") {
+                Some(hex) => i32::from_str_radix(hex, 16),
+                None => value.parse(),
+            };
+            Some(parsed.unwrap_or_else(|_| {
+                fail(&format!(
+                    "Couldn't parse the value of {}: {:?}",
+                    name, value
+                ))
+            }))
+        })
+        .collect()
+}
+
+/// Sets up linking with libintl, building it from source if necessary. Returns the directory
+/// containing `libintl.h`, or `None` if it's in the compiler's default search path.
+fn link_gettext() -> Option<PathBuf> {
+    let target = env::var("TARGET").unwrap();
 
     if cfg!(feature = "gettext-system") || env("GETTEXT_SYSTEM").is_some() {
         if target.contains("linux") && (target.contains("-gnu") || target.contains("-musl")) {
             // intl is part of glibc and musl
-            return;
+            return None;
         } else if target.contains("windows") && target.contains("-gnu") {
             // gettext doesn't come with a pkg-config file
             let gnu_root = get_windows_gnu_root();
@@ -99,7 +219,7 @@ fn main() {
             // It is needed by `cargo test` while generating doc
             println!("cargo:rustc-link-lib=dylib=pthread");
             println!("cargo:include={}/../usr/include", &gnu_root);
-            return;
+            return Some(PathBuf::from(format!("{}/../usr/include", gnu_root)));
         }
         // else can't use system gettext on this target
     }
@@ -128,11 +248,9 @@ fn main() {
             println!("cargo:rustc-link-search=native={}/lib", gettext_dir);
         }
 
-        if let Some(include) = env("GETTEXT_INCLUDE_DIR") {
-            println!("cargo:include={}", include);
-        } else {
-            println!("cargo:include={}/include", gettext_dir);
-        }
+        let include =
+            env("GETTEXT_INCLUDE_DIR").unwrap_or_else(|| format!("{}/include", gettext_dir));
+        println!("cargo:include={}", include);
 
         if env("GETTEXT_STATIC").is_some() {
             println!("cargo:rustc-link-lib=static=intl");
@@ -140,7 +258,7 @@ fn main() {
             println!("cargo:rustc-link-lib=dylib=intl");
         }
 
-        return;
+        return Some(PathBuf::from(include));
     } else if let (Some(bin), Some(lib), Some(include)) = (
         env("GETTEXT_BIN_DIR"),
         env("GETTEXT_LIB_DIR"),
@@ -156,7 +274,7 @@ fn main() {
         println!("cargo:bin={}", bin);
         println!("cargo:lib={}", lib);
         println!("cargo:include={}", include);
-        return;
+        return Some(PathBuf::from(include));
     }
 
     let host = env::var("HOST").unwrap();
@@ -273,6 +391,8 @@ fn main() {
         );
         println!("cargo:rustc-link-lib=dylib=iconv");
     }
+
+    Some(dst.join("include"))
 }
 
 fn run(cmd: &mut Command, program: &str) {
@@ -4,6 +4,66 @@ use std::os::raw::{c_char, c_int, c_ulong};
 #[allow(non_camel_case_types)]
 type wchar_t = u16;
 
+/// Numeric and monetary formatting conventions of the current locale, as returned by
+/// [`localeconv`].
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct lconv {
+    pub decimal_point: *mut c_char,
+    pub thousands_sep: *mut c_char,
+    pub grouping: *mut c_char,
+    pub int_curr_symbol: *mut c_char,
+    pub currency_symbol: *mut c_char,
+    pub mon_decimal_point: *mut c_char,
+    pub mon_thousands_sep: *mut c_char,
+    pub mon_grouping: *mut c_char,
+    pub positive_sign: *mut c_char,
+    pub negative_sign: *mut c_char,
+    pub int_frac_digits: c_char,
+    pub frac_digits: c_char,
+    pub p_cs_precedes: c_char,
+    pub p_sep_by_space: c_char,
+    pub n_cs_precedes: c_char,
+    pub n_sep_by_space: c_char,
+    pub p_sign_posn: c_char,
+    pub n_sign_posn: c_char,
+    #[cfg(not(windows))]
+    pub int_p_cs_precedes: c_char,
+    #[cfg(target_os = "linux")]
+    pub int_p_sep_by_space: c_char,
+    #[cfg(not(windows))]
+    pub int_n_cs_precedes: c_char,
+    #[cfg(not(any(windows, target_os = "linux")))]
+    pub int_p_sep_by_space: c_char,
+    #[cfg(not(windows))]
+    pub int_n_sep_by_space: c_char,
+    #[cfg(not(windows))]
+    pub int_p_sign_posn: c_char,
+    #[cfg(not(windows))]
+    pub int_n_sign_posn: c_char,
+    #[cfg(windows)]
+    pub _W_decimal_point: *mut wchar_t,
+    #[cfg(windows)]
+    pub _W_thousands_sep: *mut wchar_t,
+    #[cfg(windows)]
+    pub _W_int_curr_symbol: *mut wchar_t,
+    #[cfg(windows)]
+    pub _W_currency_symbol: *mut wchar_t,
+    #[cfg(windows)]
+    pub _W_mon_decimal_point: *mut wchar_t,
+    #[cfg(windows)]
+    pub _W_mon_thousands_sep: *mut wchar_t,
+    #[cfg(windows)]
+    pub _W_positive_sign: *mut wchar_t,
+    #[cfg(windows)]
+    pub _W_negative_sign: *mut wchar_t,
+}
+
+/// Item of locale information to query with [`nl_langinfo`].
+#[cfg(not(windows))]
+#[allow(non_camel_case_types)]
+pub type nl_item = c_int;
+
 extern "C" {
     pub fn gettext(s: *const c_char) -> *mut c_char;
     pub fn dgettext(domain: *const c_char, s: *const c_char) -> *mut c_char;
@@ -33,4 +93,146 @@ extern "C" {
     pub fn bind_textdomain_codeset(domain: *const c_char, codeset: *const c_char) -> *mut c_char;
 
     pub fn setlocale(category: c_int, locale: *const c_char) -> *mut c_char;
+
+    pub fn localeconv() -> *mut lconv;
+    #[cfg(not(windows))]
+    pub fn nl_langinfo(item: nl_item) -> *mut c_char;
+
+    /// Version of GNU libintl, in the same format as `LIBINTL_VERSION`. glibc and musl don't have
+    /// it.
+    #[cfg(gettext_libintl_version)]
+    pub static libintl_version: c_int;
+
+    /// Counter that libintl increments whenever it loads a message catalog. Not in musl.
+    #[cfg(gettext_msg_cat_cntr)]
+    pub static mut _nl_msg_cat_cntr: c_int;
+
+    /// Version of glibc, which has libintl built in.
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    pub fn gnu_get_libc_version() -> *const c_char;
 }
+
+// Items for `nl_langinfo`. glibc declares them as enumerators rather than macros, so they can't be
+// read from `langinfo.h` like the constants below; glibc and musl agree on their values, and so do
+// the BSDs.
+#[cfg(target_os = "linux")]
+mod langinfo {
+    use super::nl_item;
+
+    pub const CODESET: nl_item = 14;
+    pub const RADIXCHAR: nl_item = 0x10000;
+    pub const THOUSEP: nl_item = 0x10001;
+
+    pub const ABDAY_1: nl_item = 0x20000;
+    pub const ABDAY_2: nl_item = 0x20001;
+    pub const ABDAY_3: nl_item = 0x20002;
+    pub const ABDAY_4: nl_item = 0x20003;
+    pub const ABDAY_5: nl_item = 0x20004;
+    pub const ABDAY_6: nl_item = 0x20005;
+    pub const ABDAY_7: nl_item = 0x20006;
+    pub const DAY_1: nl_item = 0x20007;
+    pub const DAY_2: nl_item = 0x20008;
+    pub const DAY_3: nl_item = 0x20009;
+    pub const DAY_4: nl_item = 0x2000A;
+    pub const DAY_5: nl_item = 0x2000B;
+    pub const DAY_6: nl_item = 0x2000C;
+    pub const DAY_7: nl_item = 0x2000D;
+    pub const ABMON_1: nl_item = 0x2000E;
+    pub const ABMON_2: nl_item = 0x2000F;
+    pub const ABMON_3: nl_item = 0x20010;
+    pub const ABMON_4: nl_item = 0x20011;
+    pub const ABMON_5: nl_item = 0x20012;
+    pub const ABMON_6: nl_item = 0x20013;
+    pub const ABMON_7: nl_item = 0x20014;
+    pub const ABMON_8: nl_item = 0x20015;
+    pub const ABMON_9: nl_item = 0x20016;
+    pub const ABMON_10: nl_item = 0x20017;
+    pub const ABMON_11: nl_item = 0x20018;
+    pub const ABMON_12: nl_item = 0x20019;
+    pub const MON_1: nl_item = 0x2001A;
+    pub const MON_2: nl_item = 0x2001B;
+    pub const MON_3: nl_item = 0x2001C;
+    pub const MON_4: nl_item = 0x2001D;
+    pub const MON_5: nl_item = 0x2001E;
+    pub const MON_6: nl_item = 0x2001F;
+    pub const MON_7: nl_item = 0x20020;
+    pub const MON_8: nl_item = 0x20021;
+    pub const MON_9: nl_item = 0x20022;
+    pub const MON_10: nl_item = 0x20023;
+    pub const MON_11: nl_item = 0x20024;
+    pub const MON_12: nl_item = 0x20025;
+
+    /// Paper height in millimetres, returned in place of a pointer. A glibc extension.
+    #[cfg(target_env = "gnu")]
+    pub const _NL_PAPER_HEIGHT: nl_item = 0x70000;
+    /// Paper width in millimetres, returned in place of a pointer. A glibc extension.
+    #[cfg(target_env = "gnu")]
+    pub const _NL_PAPER_WIDTH: nl_item = 0x70001;
+}
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly"
+))]
+mod langinfo {
+    use super::nl_item;
+
+    pub const CODESET: nl_item = 0;
+    pub const RADIXCHAR: nl_item = 50;
+    pub const THOUSEP: nl_item = 51;
+
+    pub const DAY_1: nl_item = 7;
+    pub const DAY_2: nl_item = 8;
+    pub const DAY_3: nl_item = 9;
+    pub const DAY_4: nl_item = 10;
+    pub const DAY_5: nl_item = 11;
+    pub const DAY_6: nl_item = 12;
+    pub const DAY_7: nl_item = 13;
+    pub const ABDAY_1: nl_item = 14;
+    pub const ABDAY_2: nl_item = 15;
+    pub const ABDAY_3: nl_item = 16;
+    pub const ABDAY_4: nl_item = 17;
+    pub const ABDAY_5: nl_item = 18;
+    pub const ABDAY_6: nl_item = 19;
+    pub const ABDAY_7: nl_item = 20;
+    pub const MON_1: nl_item = 21;
+    pub const MON_2: nl_item = 22;
+    pub const MON_3: nl_item = 23;
+    pub const MON_4: nl_item = 24;
+    pub const MON_5: nl_item = 25;
+    pub const MON_6: nl_item = 26;
+    pub const MON_7: nl_item = 27;
+    pub const MON_8: nl_item = 28;
+    pub const MON_9: nl_item = 29;
+    pub const MON_10: nl_item = 30;
+    pub const MON_11: nl_item = 31;
+    pub const MON_12: nl_item = 32;
+    pub const ABMON_1: nl_item = 33;
+    pub const ABMON_2: nl_item = 34;
+    pub const ABMON_3: nl_item = 35;
+    pub const ABMON_4: nl_item = 36;
+    pub const ABMON_5: nl_item = 37;
+    pub const ABMON_6: nl_item = 38;
+    pub const ABMON_7: nl_item = 39;
+    pub const ABMON_8: nl_item = 40;
+    pub const ABMON_9: nl_item = 41;
+    pub const ABMON_10: nl_item = 42;
+    pub const ABMON_11: nl_item = 43;
+    pub const ABMON_12: nl_item = 44;
+}
+#[cfg(any(
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly"
+))]
+pub use langinfo::*;
+
+// Locale categories, e.g. `LC_MESSAGES`, with their values from the target's `locale.h`. Categories
+// that the target doesn't have are set to -1.
+//
+// Also `LIBINTL_VERSION`, the version of GNU libintl headers (e.g. 0x001500 for 0.21), if they are
+// the ones in use.
+include!(concat!(env!("OUT_DIR"), "/constants.rs"));
@@ -1,6 +1,7 @@
 extern crate ctest2;
 
 use std::env;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::{self, Command};
 
@@ -13,6 +14,24 @@ fn main() {
 
     cfg.header("locale.h");
     cfg.header("libintl.h");
+    if env::var("CARGO_CFG_TARGET_FAMILY").unwrap() == "unix" {
+        cfg.header("langinfo.h");
+    }
+    if env::var("CARGO_CFG_TARGET_OS").unwrap() == "linux"
+        && env::var("CARGO_CFG_TARGET_ENV").unwrap() == "gnu"
+    {
+        cfg.header("gnu/libc-version.h");
+    }
+
+    // Declarations that depend on the libintl implementation, as detected by gettext-sys
+    if env::var_os("DEP_GETTEXT_LIBINTL_VERSION").is_some() {
+        cfg.cfg("gettext_libintl_version", None);
+    }
+    if env::var_os("DEP_GETTEXT_MSG_CAT_CNTR").is_some() {
+        cfg.cfg("gettext_msg_cat_cntr", None);
+    }
+    // _nl_msg_cat_cntr isn't declared in any header
+    cfg.skip_static(|name| name == "_nl_msg_cat_cntr");
 
     // Skip ptr check because the symbol name is different between glibc
     // implementation and static lib.
@@ -22,7 +41,29 @@ fn main() {
         cfg.skip_fn_ptrcheck(|_| true);
     }
 
-    cfg.generate("../gettext-sys/lib.rs", "all.rs");
+    // ctest can't expand `include!`, so the constants generated by gettext-sys' build script are
+    // pasted into a copy of its source instead
+    let constants = env::var("DEP_GETTEXT_CONSTANTS").unwrap();
+    let krate = fs::read_to_string("../gettext-sys/lib.rs")
+        .unwrap()
+        .replace(
+            "include!(concat!(env!(\"OUT_DIR\"), \"/constants.rs\"));",
+            &fs::read_to_string(constants).unwrap(),
+        );
+    let krate_path = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("gettext_sys.rs");
+    fs::write(&krate_path, krate).unwrap();
+    println!("cargo:rerun-if-changed=../gettext-sys/lib.rs");
+
+    // Categories missing from locale.h are set to -1, and can't be compared against C
+    let missing = env::var("DEP_GETTEXT_MISSING_LOCALE_CATEGORIES").unwrap_or_default();
+    let missing: Vec<String> = missing
+        .split(',')
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect();
+    cfg.skip_const(move |name| missing.iter().any(|m| m == name));
+
+    cfg.generate(&krate_path, "all.rs");
 
     // Check that we can find and run gettext binary
     let cmd = if let Some(bin) = env::var_os("DEP_GETTEXT_BIN") {
@@ -1,7 +1,9 @@
 #![allow(bad_style)]
 
 extern crate gettext_sys;
+extern crate libc;
 
 use gettext_sys::*;
+use std::os::raw::c_int;
 
 include!(concat!(env!("OUT_DIR"), "/all.rs"));